@@ -3,7 +3,7 @@
 use cosmwasm_std::{entry_point, Reply};
 use cosmwasm_std::{to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult};
 
-use crate::ibc::types::stargate::channel::new_ica_channel_open_init_cosmos_msg;
+use crate::ibc::handshake::channel_open_submsg;
 use crate::types::keys;
 use crate::types::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use crate::types::state::{self, ChannelState, ContractState};
@@ -29,22 +29,28 @@ pub fn instantiate(
         .transpose()?;
 
     // Save the admin. Ica address is determined during handshake.
-    state::STATE.save(deps.storage, &ContractState::new(callback_address))?;
-
-    state::CHANNEL_OPEN_INIT_OPTIONS.save(deps.storage, &msg.channel_open_init_options)?;
+    state::STATE.save(
+        deps.storage,
+        &ContractState {
+            backend: msg.backend.unwrap_or_default(),
+            ..ContractState::new(callback_address)
+        },
+    )?;
+
+    // The custom host ports are allowed before the channel opened on instantiation is validated.
+    if let Some(ports) = msg.allowed_host_ports {
+        execute::validate_host_ports(&ports)?;
+        state::ALLOWED_HOST_PORTS.save(deps.storage, &ports)?;
+    }
 
-    state::ALLOW_CHANNEL_OPEN_INIT.save(deps.storage, &true)?;
+    // The channel opened on instantiation belongs to the ICA with id 0.
+    execute::verify_host_chain_id(&deps.querier, &msg.channel_open_init_options)?;
+    execute::save_channel_open_init_options(deps.storage, 0, &msg.channel_open_init_options)?;
 
-    let ica_channel_open_init_msg = new_ica_channel_open_init_cosmos_msg(
-        env.contract.address.to_string(),
-        msg.channel_open_init_options.connection_id,
-        msg.channel_open_init_options.counterparty_port_id,
-        msg.channel_open_init_options.counterparty_connection_id,
-        None,
-        msg.channel_open_init_options.channel_ordering,
-    );
+    let channel_open_submsg =
+        channel_open_submsg(deps.storage, &env, 0, msg.channel_open_init_options)?;
 
-    Ok(Response::new().add_message(ica_channel_open_init_msg))
+    Ok(Response::new().add_submessage(channel_open_submsg))
 }
 
 /// Handles the execution of the contract.
@@ -59,25 +65,106 @@ pub fn execute(
     match msg {
         ExecuteMsg::CreateChannel {
             channel_open_init_options,
-        } => execute::create_channel(deps, env, info, channel_open_init_options),
-        ExecuteMsg::CloseChannel {} => execute::close_channel(deps, info),
+            ica_id,
+            allow_new_ica_address,
+        } => execute::create_channel(
+            deps,
+            env,
+            info,
+            ica_id,
+            channel_open_init_options,
+            allow_new_ica_address,
+        ),
+        ExecuteMsg::CloseChannel { ica_id } => execute::close_channel(deps, info, ica_id),
         ExecuteMsg::UpdateCallbackAddress { callback_address } => {
             execute::update_callback_address(deps, info, callback_address)
         }
         ExecuteMsg::SendCosmosMsgs {
+            ica_id,
             messages,
             queries,
             packet_memo,
             timeout_seconds,
+            timeout,
+            retry,
         } => execute::send_cosmos_msgs(
             deps,
             env,
             info,
+            ica_id,
+            messages,
+            queries,
+            packet_memo,
+            timeout_seconds,
+            timeout,
+            retry,
+        ),
+        ExecuteMsg::Enqueue {
+            ica_id,
             messages,
             queries,
+            correlation_id,
+        } => execute::enqueue(deps, info, ica_id, messages, queries, correlation_id),
+        ExecuteMsg::Flush {
+            ica_id,
+            packet_memo,
+            timeout_seconds,
+            timeout,
+            retry,
+        } => execute::flush(
+            deps,
+            env,
+            info,
+            ica_id,
             packet_memo,
             timeout_seconds,
+            timeout,
+            retry,
+        ),
+        ExecuteMsg::Dequeue {
+            ica_id,
+            correlation_id,
+        } => execute::dequeue(deps, info, ica_id, correlation_id),
+        ExecuteMsg::ClearQueue { ica_id } => execute::clear_queue(deps, info, ica_id),
+        ExecuteMsg::UpdatePacketHistoryRetention { retention } => {
+            execute::update_packet_history_retention(deps, info, retention)
+        }
+        ExecuteMsg::UpdateDefaultTimeout { timeout_seconds } => {
+            execute::update_default_timeout(deps, info, timeout_seconds)
+        }
+        ExecuteMsg::UpdateEnqueuers { enqueuers } => {
+            execute::update_enqueuers(deps, info, enqueuers)
+        }
+        ExecuteMsg::UpdateAllowedHostPorts { ports } => {
+            execute::update_allowed_host_ports(deps, info, ports)
+        }
+        ExecuteMsg::UpdateAutoReopenPolicy { ica_id, policy } => {
+            execute::update_auto_reopen_policy(deps, info, ica_id, policy)
+        }
+        ExecuteMsg::RefreshHostAllowMessages { ica_id } => {
+            execute::refresh_host_allow_messages(deps, env, info, ica_id)
+        }
+        ExecuteMsg::SyncChannel { ica_id } => execute::sync_channel(deps, env, ica_id),
+        ExecuteMsg::RegisterQuerySubscription {
+            ica_id,
+            queries,
+            interval_seconds,
+            cache_results,
+            bounty,
+        } => execute::register_query_subscription(
+            deps,
+            env,
+            info,
+            ica_id,
+            queries,
+            interval_seconds,
+            cache_results,
+            bounty,
         ),
+        ExecuteMsg::CancelQuerySubscription { subscription_id } => {
+            execute::cancel_query_subscription(deps, info, subscription_id)
+        }
+        ExecuteMsg::Crank { limit } => execute::crank(deps, env, info, limit),
         ExecuteMsg::UpdateOwnership(action) => execute::update_ownership(deps, env, info, action),
     }
 }
@@ -87,7 +174,12 @@ pub fn execute(
 #[allow(clippy::pedantic)]
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
     match msg.id {
-        keys::reply_ids::SEND_QUERY_PACKET => reply::send_query_packet(deps, env, msg.result),
+        keys::reply_ids::SEND_PACKET => reply::send_packet(deps, env, msg.payload, msg.result),
+        keys::reply_ids::RESEND_PACKET => reply::resend_packet(deps, env, msg.payload, msg.result),
+        keys::reply_ids::REGISTER_INTERCHAIN_ACCOUNT => {
+            reply::register_interchain_account(deps, env, msg.payload, msg.result)
+        }
+        keys::reply_ids::CHANNEL_CLOSE_CALLBACK => Ok(reply::channel_close_callback(&msg.result)),
         _ => Err(ContractError::UnknownReplyId(msg.id)),
     }
 }
@@ -98,7 +190,45 @@ pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractEr
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetContractState {} => to_json_binary(&query::state(deps)?),
-        QueryMsg::GetChannel {} => to_json_binary(&query::channel(deps)?),
+        QueryMsg::GetChannel { ica_id } => to_json_binary(&query::channel(deps, ica_id)?),
+        QueryMsg::GetIcaInfo { ica_id } => to_json_binary(&query::ica_info(deps, ica_id)?),
+        QueryMsg::GetAllowedHostPorts {} => to_json_binary(&query::allowed_host_ports(deps)?),
+        QueryMsg::GetAutoReopenPolicy { ica_id } => {
+            to_json_binary(&query::auto_reopen_policy(deps, ica_id)?)
+        }
+        QueryMsg::GetDefaultTimeout {} => to_json_binary(&query::default_timeout(deps)?),
+        QueryMsg::GetEnqueuers {} => to_json_binary(&query::enqueuers(deps)?),
+        QueryMsg::GetQueue { ica_id } => to_json_binary(&query::queue(deps, ica_id)?),
+        QueryMsg::GetHostAllowMessages { ica_id } => {
+            to_json_binary(&query::host_allow_messages(deps, ica_id)?)
+        }
+        QueryMsg::GetChannelHistory {
+            ica_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query::channel_history(deps, ica_id, start_after, limit)?),
+        QueryMsg::GetPendingPackets { start_after, limit } => {
+            to_json_binary(&query::pending_packets(deps, start_after, limit)?)
+        }
+        QueryMsg::GetPacket {
+            ica_id,
+            sequence,
+            channel_id,
+        } => to_json_binary(&query::packet(deps, ica_id, sequence, channel_id)?),
+        QueryMsg::GetPacketHistory {
+            start_after,
+            limit,
+            status,
+        } => to_json_binary(&query::packet_history(deps, start_after, limit, status)?),
+        QueryMsg::GetPacketHistoryRetention {} => {
+            to_json_binary(&query::packet_history_retention(deps)?)
+        }
+        QueryMsg::GetQuerySubscription { subscription_id } => {
+            to_json_binary(&query::query_subscription(deps, subscription_id)?)
+        }
+        QueryMsg::GetQuerySubscriptionResult { subscription_id } => {
+            to_json_binary(&query::query_subscription_result(deps, subscription_id)?)
+        }
         QueryMsg::Ownership {} => to_json_binary(&cw_ownable::get_ownership(deps.storage)?),
     }
 }
@@ -108,28 +238,60 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
 #[allow(clippy::pedantic)]
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
     migrate::validate_semver(deps.as_ref())?;
-    migrate::validate_channel_encoding(deps.as_ref())?;
 
-    cw2::set_contract_version(deps.storage, keys::CONTRACT_NAME, keys::CONTRACT_VERSION)?;
     // If state structure changed in any contract version in the way migration is needed, it
     // should occur here
+    migrate::single_ica_state(deps.storage)?;
+
+    cw2::set_contract_version(deps.storage, keys::CONTRACT_NAME, keys::CONTRACT_VERSION)?;
 
     Ok(Response::default())
 }
 
 mod execute {
-    use cosmwasm_std::{CosmosMsg, IbcMsg, SubMsg};
-
-    use crate::{ibc::types::packet::IcaPacketData, types::msg::options::ChannelOpenInitOptions};
+    use cosmwasm_std::{
+        Addr, BankMsg, Binary, Coin, CosmosMsg, IbcMsg, IbcTimeout, Order, QuerierWrapper, Storage,
+        SubMsg,
+    };
+    use cw_storage_plus::Bound;
+
+    use crate::{
+        ibc::{
+            handshake::{channel_close_callback, ibc_channel_open},
+            relay::{scheduled_retry_submsgs, send_packet_submsg},
+            types::{
+                events,
+                metadata::{IcaMetadata, TxEncoding},
+                packet::{IcaPacketData, DEFAULT_TIMEOUT_SECONDS},
+                stargate::{channel::query_channel, connection::query_counterparty_chain_id},
+            },
+        },
+        types::{
+            msg::{
+                options::{
+                    AutoReopenPolicy, ChannelOpenInitOptions, IcaBackend, PacketTimeout,
+                    RetryPolicy,
+                },
+                DEFAULT_CRANK_LIMIT, MAX_QUEUED_PACKET_SIZE,
+            },
+            query_msg,
+            state::{
+                ica_query::Subscription,
+                queue::{Correlation, QueueEntry},
+                retry::PacketRetry,
+                IcaInfo,
+            },
+        },
+    };
 
     use super::{
-        keys, new_ica_channel_open_init_cosmos_msg, state, ContractError, DepsMut, Env,
-        MessageInfo, Response,
+        channel_open_submsg, state, ContractError, DepsMut, Env, MessageInfo, Response, StdResult,
     };
 
     use cosmwasm_std::{Empty, QueryRequest};
 
-    /// Submits a stargate `MsgChannelOpenInit` to the chain.
+    /// Submits a stargate `MsgChannelOpenInit` or `MsgRegisterInterchainAccount` to the chain,
+    /// depending on the backend of the contract.
     /// Can only be called by the contract owner or a whitelisted address.
     /// Only the contract owner can include the channel open init options.
     #[allow(clippy::needless_pass_by_value)]
@@ -137,39 +299,108 @@ mod execute {
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
+        ica_id: u64,
         options: Option<ChannelOpenInitOptions>,
+        allow_new_ica_address: bool,
     ) -> Result<Response, ContractError> {
         cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
         let options = if let Some(new_options) = options {
-            state::CHANNEL_OPEN_INIT_OPTIONS.save(deps.storage, &new_options)?;
+            verify_host_chain_id(&deps.querier, &new_options)?;
+            save_channel_open_init_options(deps.storage, ica_id, &new_options)?;
             new_options
         } else {
             state::CHANNEL_OPEN_INIT_OPTIONS
-                .may_load(deps.storage)?
+                .may_load(deps.storage, ica_id)?
                 .ok_or(ContractError::NoChannelInitOptions)?
         };
 
-        state::ALLOW_CHANNEL_OPEN_INIT.save(deps.storage, &true)?;
+        if allow_new_ica_address {
+            state::ALLOW_NEW_ICA_ADDRESS.save(deps.storage, ica_id, &true)?;
+        } else {
+            state::ALLOW_NEW_ICA_ADDRESS.remove(deps.storage, ica_id);
+        }
+
+        let channel_open_submsg = channel_open_submsg(deps.storage, &env, ica_id, options)?;
+
+        Ok(Response::new().add_submessage(channel_open_submsg))
+    }
+
+    /// Checks that the connection in the options points to the expected host chain, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the client state of the connection cannot be queried, or if its
+    /// chain id is not the expected one.
+    pub fn verify_host_chain_id(
+        querier: &QuerierWrapper,
+        options: &ChannelOpenInitOptions,
+    ) -> Result<(), ContractError> {
+        let Some(expected) = &options.expected_host_chain_id else {
+            return Ok(());
+        };
+
+        let actual = query_counterparty_chain_id(querier, &options.connection_id)?;
+        if &actual != expected {
+            return Err(ContractError::HostChainIdMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+
+        Ok(())
+    }
 
-        let ica_channel_open_init_msg = new_ica_channel_open_init_cosmos_msg(
-            env.contract.address.to_string(),
-            options.connection_id,
-            options.counterparty_port_id,
-            options.counterparty_connection_id,
-            None,
-            options.channel_ordering,
-        );
+    /// Saves the channel open init options of an ICA, and reserves the connection for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is already used by another ICA.
+    pub fn save_channel_open_init_options(
+        storage: &mut dyn Storage,
+        ica_id: u64,
+        options: &ChannelOpenInitOptions,
+    ) -> Result<(), ContractError> {
+        match state::CONNECTION_ICA_ID.may_load(storage, &options.connection_id)? {
+            Some(other_ica_id) if other_ica_id != ica_id => {
+                return Err(ContractError::ConnectionUsedByOtherIca {
+                    connection_id: options.connection_id.clone(),
+                    ica_id: other_ica_id,
+                })
+            }
+            _ => {}
+        }
+
+        // Release the connection that the ICA used before, if it changed.
+        if let Some(prev_options) = state::CHANNEL_OPEN_INIT_OPTIONS.may_load(storage, ica_id)? {
+            if prev_options.connection_id != options.connection_id {
+                state::CONNECTION_ICA_ID.remove(storage, &prev_options.connection_id);
+            }
+        }
 
-        Ok(Response::new().add_message(ica_channel_open_init_msg))
+        state::CONNECTION_ICA_ID.save(storage, &options.connection_id, &ica_id)?;
+        state::CHANNEL_OPEN_INIT_OPTIONS.save(storage, ica_id, options)?;
+
+        Ok(())
     }
 
     /// Submits a [`IbcMsg::CloseChannel`].
     #[allow(clippy::needless_pass_by_value)]
-    pub fn close_channel(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    pub fn close_channel(
+        deps: DepsMut,
+        info: MessageInfo,
+        ica_id: u64,
+    ) -> Result<Response, ContractError> {
         cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-        let channel_state = state::CHANNEL_STATE.load(deps.storage)?;
+        // The controller module does not allow the owner of a channel to close it.
+        if state::STATE.load(deps.storage)?.backend == IcaBackend::ControllerModule {
+            return Err(ContractError::UnsupportedByControllerModule(
+                "CloseChannel".to_string(),
+            ));
+        }
+
+        let channel_state = state::CHANNEL_STATE.load(deps.storage, ica_id)?;
         if !channel_state.is_open() {
             return Err(ContractError::InvalidChannelStatus {
                 expected: state::ChannelStatus::Open.to_string(),
@@ -187,235 +418,2819 @@ mod execute {
     }
 
     /// Sends an array of [`CosmosMsg`] to the ICA host.
-    #[allow(clippy::needless_pass_by_value)]
+    #[allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
     pub fn send_cosmos_msgs(
         deps: DepsMut,
         env: Env,
         info: MessageInfo,
+        ica_id: u64,
         messages: Vec<CosmosMsg>,
         queries: Vec<QueryRequest<Empty>>,
         packet_memo: Option<String>,
         timeout_seconds: Option<u64>,
+        timeout: Option<PacketTimeout>,
+        retry: Option<RetryPolicy>,
     ) -> Result<Response, ContractError> {
         cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-        let contract_state = state::STATE.load(deps.storage)?;
-        let ica_info = contract_state.get_ica_info()?;
-        let has_queries = !queries.is_empty();
+        let ica_info = state::load_ica_info(deps.storage, ica_id)?;
+        assert_retry_timeout(retry.as_ref(), timeout.as_ref())?;
+        let timeout = packet_timeout(deps.storage, &env, timeout_seconds, timeout)?;
 
-        let ica_packet = IcaPacketData::from_cosmos_msgs(
-            deps.storage,
+        let (ica_packet, query_paths) = IcaPacketData::from_cosmos_msgs_with_query_paths(
             messages,
             queries,
             &ica_info.encoding,
             packet_memo,
             &ica_info.ica_address,
         )?;
-        let send_packet_msg = ica_packet.to_ibc_msg(&env, ica_info.channel_id, timeout_seconds)?;
 
-        let send_packet_submsg = if has_queries {
-            // TODO: use payload when we switch to cosmwasm_2_0 feature
-            SubMsg::reply_on_success(send_packet_msg, keys::reply_ids::SEND_QUERY_PACKET)
-        } else {
-            SubMsg::new(send_packet_msg)
-        };
+        assert_allowed_by_host(deps.storage, ica_id, &ica_packet, &ica_info.encoding)?;
+
+        let retry = packet_retry(deps.storage, retry, timeout_seconds, &ica_packet)?;
+        let send_packet_submsg = send_packet_submsg(
+            deps.storage,
+            &env,
+            &info.sender,
+            ica_id,
+            ica_info.channel_id,
+            &ica_packet,
+            &query_paths,
+            timeout,
+            None,
+            vec![],
+            retry,
+        )?;
 
         Ok(Response::default().add_submessage(send_packet_submsg))
     }
 
-    /// Update the ownership of the contract.
+    /// Adds an entry to the queue of an ICA.
+    /// Can only be called by the contract owner or an enqueuer.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn update_ownership(
+    pub fn enqueue(
         deps: DepsMut,
-        env: Env,
         info: MessageInfo,
-        action: cw_ownable::Action,
+        ica_id: u64,
+        messages: Vec<CosmosMsg>,
+        queries: Vec<QueryRequest<Empty>>,
+        correlation_id: Option<String>,
     ) -> Result<Response, ContractError> {
-        if action == cw_ownable::Action::RenounceOwnership {
-            return Err(ContractError::OwnershipCannotBeRenounced);
+        assert_enqueuer(deps.storage, &info.sender)?;
+
+        if messages.is_empty() && queries.is_empty() {
+            return Err(ContractError::EmptyQueueEntry);
+        }
+
+        let ica_info = state::load_ica_info(deps.storage, ica_id)?;
+        let mut entry = QueueEntry {
+            sender: info.sender,
+            correlation_id,
+            messages,
+            queries,
+            size: 0,
         };
 
-        cw_ownable::update_ownership(deps, &env.block, &info.sender, action)?;
+        // The packet of the entry is built now so that an entry which cannot be sent is rejected
+        // before it prevents the other entries from being flushed. The sizes of the entries are
+        // summed, which is an upper bound of the size of the packet sent by the flush.
+        let (ica_packet, _, _) = queued_packet(std::slice::from_ref(&entry), &ica_info, None)?;
+        assert_allowed_by_host(deps.storage, ica_id, &ica_packet, &ica_info.encoding)?;
+
+        let mut queue_info = state::QUEUE_INFO
+            .may_load(deps.storage, ica_id)?
+            .unwrap_or_default();
+        entry.size = ica_packet.data.len();
+        let size = queue_info.size + entry.size;
+        if size > MAX_QUEUED_PACKET_SIZE {
+            return Err(ContractError::QueuedPacketTooLarge {
+                size,
+                max: MAX_QUEUED_PACKET_SIZE,
+            });
+        }
 
-        Ok(Response::default())
+        state::QUEUE.save(deps.storage, (ica_id, queue_info.next_entry_id), &entry)?;
+        queue_info.next_entry_id += 1;
+        queue_info.length += 1;
+        queue_info.size = size;
+        state::QUEUE_INFO.save(deps.storage, ica_id, &queue_info)?;
+
+        Ok(Response::default().add_attribute("queue_length", queue_info.length.to_string()))
     }
 
-    /// Updates the callback address.
+    /// Removes the entries of the sender with the given correlation id from the queue of an ICA.
+    /// Can be called by anyone, for their own entries.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn update_callback_address(
+    pub fn dequeue(
         deps: DepsMut,
         info: MessageInfo,
-        callback_address: Option<String>,
+        ica_id: u64,
+        correlation_id: String,
     ) -> Result<Response, ContractError> {
-        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+        let entries = state::QUEUE
+            .prefix(ica_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .filter(|item| {
+                item.as_ref().map_or(true, |(_, entry)| {
+                    entry.sender == info.sender
+                        && entry.correlation_id.as_ref() == Some(&correlation_id)
+                })
+            })
+            .collect::<StdResult<Vec<_>>>()?;
+        if entries.is_empty() {
+            return Err(ContractError::QueueEntryNotFound(correlation_id));
+        }
 
-        let mut contract_state = state::STATE.load(deps.storage)?;
+        let mut queue_info = state::QUEUE_INFO.load(deps.storage, ica_id)?;
+        for (entry_id, entry) in &entries {
+            state::QUEUE.remove(deps.storage, (ica_id, *entry_id));
+            queue_info.length -= 1;
+            queue_info.size -= entry.size;
+        }
+        state::QUEUE_INFO.save(deps.storage, ica_id, &queue_info)?;
 
-        contract_state.callback_address = callback_address
-            .map(|addr| deps.api.addr_validate(&addr))
-            .transpose()?;
+        Ok(Response::default().add_attribute("queue_length", queue_info.length.to_string()))
+    }
 
-        state::STATE.save(deps.storage, &contract_state)?;
+    /// Removes all the entries from the queue of an ICA.
+    /// Can only be called by the contract owner.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn clear_queue(
+        deps: DepsMut,
+        info: MessageInfo,
+        ica_id: u64,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-        Ok(Response::default())
+        let entry_ids = state::QUEUE
+            .prefix(ica_id)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        remove_queue(deps.storage, ica_id, &entry_ids);
+
+        Ok(Response::default().add_attribute("removed_entries", entry_ids.len().to_string()))
     }
-}
 
-mod reply {
-    use cosmwasm_std::SubMsgResult;
+    /// Sends the queue of an ICA in a single packet and empties the queue.
+    /// Can only be called by the contract owner or an enqueuer.
+    #[allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
+    pub fn flush(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        ica_id: u64,
+        packet_memo: Option<String>,
+        timeout_seconds: Option<u64>,
+        timeout: Option<PacketTimeout>,
+        retry: Option<RetryPolicy>,
+    ) -> Result<Response, ContractError> {
+        assert_enqueuer(deps.storage, &info.sender)?;
+
+        let (entry_ids, queue): (Vec<u64>, Vec<QueueEntry>) = state::QUEUE
+            .prefix(ica_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        if queue.is_empty() {
+            return Err(ContractError::QueueEmpty(ica_id));
+        }
+
+        let ica_info = state::load_ica_info(deps.storage, ica_id)?;
+        assert_retry_timeout(retry.as_ref(), timeout.as_ref())?;
+        let timeout = packet_timeout(deps.storage, &env, timeout_seconds, timeout)?;
+        let (ica_packet, query_paths, correlations) =
+            queued_packet(&queue, &ica_info, packet_memo)?;
+
+        // The host's allow list may have been refreshed since the entries were added. The entries
+        // that are no longer allowed can be removed with `Dequeue` or `ClearQueue`.
+        assert_allowed_by_host(deps.storage, ica_id, &ica_packet, &ica_info.encoding)?;
+
+        remove_queue(deps.storage, ica_id, &entry_ids);
+
+        let retry = packet_retry(deps.storage, retry, timeout_seconds, &ica_packet)?;
+        let send_packet_submsg = send_packet_submsg(
+            deps.storage,
+            &env,
+            &info.sender,
+            ica_id,
+            ica_info.channel_id,
+            &ica_packet,
+            &query_paths,
+            timeout,
+            None,
+            correlations,
+            retry,
+        )?;
 
-    use super::{state, ContractError, DepsMut, Env, Response};
+        Ok(Response::default().add_submessage(send_packet_submsg))
+    }
 
-    /// Handles the reply to the query packet.
+    /// Sets or resets the retention limit of the packet history.
+    /// The outcomes above the new limit are removed as new outcomes are recorded.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn send_query_packet(
+    pub fn update_packet_history_retention(
         deps: DepsMut,
-        _env: Env,
-        result: SubMsgResult,
+        info: MessageInfo,
+        retention: Option<u64>,
     ) -> Result<Response, ContractError> {
-        match result {
-            SubMsgResult::Ok(resp) => {
-                #[allow(deprecated)] // TODO: Remove deprecated `.data` field
-                let sequence = anybuf::Bufany::deserialize(&resp.data.unwrap_or_default())?
-                    .uint64(1)
-                    .unwrap();
-                let channel_id = state::STATE.load(deps.storage)?.get_ica_info()?.channel_id;
-                let query_paths = state::QUERY.load(deps.storage)?;
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-                state::QUERY.remove(deps.storage);
-                state::PENDING_QUERIES.save(deps.storage, (&channel_id, sequence), &query_paths)?;
+        match retention {
+            Some(retention) => state::PACKET_HISTORY_RETENTION.save(deps.storage, &retention)?,
+            None => state::PACKET_HISTORY_RETENTION.remove(deps.storage),
+        }
 
-                Ok(Response::default())
+        Ok(Response::default())
+    }
+
+    /// Sets or resets the default timeout of the packets.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_default_timeout(
+        deps: DepsMut,
+        info: MessageInfo,
+        timeout_seconds: Option<u64>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+        match timeout_seconds {
+            Some(0) => {
+                return Err(ContractError::InvalidPacketTimeout(
+                    "timeout_seconds must be greater than zero".to_string(),
+                ))
             }
-            SubMsgResult::Err(err) => unreachable!("query packet failed: {err}"),
+            Some(timeout_seconds) => state::DEFAULT_TIMEOUT.save(deps.storage, &timeout_seconds)?,
+            None => state::DEFAULT_TIMEOUT.remove(deps.storage),
         }
+
+        Ok(Response::default())
     }
-}
 
-mod query {
-    use super::{state, ChannelState, ContractState, Deps, StdResult};
+    /// Replaces the addresses that are allowed to enqueue messages.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_enqueuers(
+        deps: DepsMut,
+        info: MessageInfo,
+        enqueuers: Vec<String>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-    /// Returns the saved contract state.
-    pub fn state(deps: Deps) -> StdResult<ContractState> {
-        state::STATE.load(deps.storage)
-    }
+        let enqueuers = enqueuers
+            .iter()
+            .map(|addr| deps.api.addr_validate(addr))
+            .collect::<StdResult<Vec<_>>>()?;
+
+        state::ENQUEUERS.save(deps.storage, &enqueuers)?;
 
-    /// Returns the saved channel state if it exists.
-    pub fn channel(deps: Deps) -> StdResult<ChannelState> {
-        state::CHANNEL_STATE.load(deps.storage)
+        Ok(Response::default())
     }
-}
 
-mod migrate {
-    use super::{keys, state, ContractError, Deps};
+    /// Replaces the allowed host ports.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_allowed_host_ports(
+        deps: DepsMut,
+        info: MessageInfo,
+        ports: Vec<String>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-    /// Validate that the contract version is semver compliant
-    /// and greater than the previous version.
-    pub fn validate_semver(deps: Deps) -> Result<(), ContractError> {
-        let prev_cw2_version = cw2::get_contract_version(deps.storage)?;
-        if prev_cw2_version.contract != keys::CONTRACT_NAME {
-            return Err(ContractError::InvalidMigrationVersion {
-                expected: keys::CONTRACT_NAME.to_string(),
-                actual: prev_cw2_version.contract,
-            });
-        }
+        validate_host_ports(&ports)?;
+        state::ALLOWED_HOST_PORTS.save(deps.storage, &ports)?;
 
-        let version: semver::Version = keys::CONTRACT_VERSION.parse()?;
-        let prev_version: semver::Version = prev_cw2_version.version.parse()?;
-        if prev_version >= version {
-            return Err(ContractError::InvalidMigrationVersion {
-                expected: format!("> {prev_version}"),
-                actual: keys::CONTRACT_VERSION.to_string(),
-            });
-        }
-        Ok(())
+        Ok(Response::default())
     }
 
-    /// Validate that the channel encoding is protobuf if set.
-    pub fn validate_channel_encoding(deps: Deps) -> Result<(), ContractError> {
-        // Reject the migration if the channel encoding is not protobuf
-        if let Some(ica_info) = state::STATE.load(deps.storage)?.ica_info {
-            if !matches!(
-                ica_info.encoding,
-                crate::ibc::types::metadata::TxEncoding::Protobuf
-            ) {
-                return Err(ContractError::UnsupportedPacketEncoding(
-                    ica_info.encoding.to_string(),
-                ));
-            }
+    /// Rejects the identifiers that IBC does not allow as port ids.
+    pub fn validate_host_ports(ports: &[String]) -> Result<(), ContractError> {
+        let is_valid_port_id = |port_id: &String| {
+            (2..=128).contains(&port_id.len())
+                && port_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._+-#[]<>".contains(c))
+        };
+        if !ports.iter().all(is_valid_port_id) {
+            return Err(ContractError::InvalidHostPort);
         }
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::types::msg::options::ChannelOpenInitOptions;
+    /// Sets or removes the auto reopen policy of an ICA, and resets its reopen attempts.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_auto_reopen_policy(
+        deps: DepsMut,
+        info: MessageInfo,
+        ica_id: u64,
+        policy: Option<AutoReopenPolicy>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
 
-    use super::*;
-    use cosmwasm_std::testing::{message_info, mock_dependencies, mock_env};
-    use cosmwasm_std::{Api, StdError, SubMsg};
+        state::AUTO_REOPEN_ATTEMPTS.remove(deps.storage, ica_id);
 
-    #[test]
-    fn test_instantiate() {
-        let mut deps = mock_dependencies();
+        match policy {
+            Some(policy) if policy.max_attempts == 0 => {
+                return Err(ContractError::InvalidAutoReopenPolicy)
+            }
+            Some(policy) => state::AUTO_REOPEN_POLICY.save(deps.storage, ica_id, &policy)?,
+            None => state::AUTO_REOPEN_POLICY.remove(deps.storage, ica_id),
+        }
 
-        let creator = deps.api.addr_make("creator");
-        let info = message_info(&creator, &[]);
-        let env = mock_env();
+        Ok(Response::default())
+    }
 
-        let channel_open_init_options = ChannelOpenInitOptions {
-            connection_id: "connection-0".to_string(),
-            counterparty_connection_id: "connection-1".to_string(),
-            counterparty_port_id: None,
-            channel_ordering: None,
-        };
+    /// Updates the channel status of the ICA from the channel state on this chain.
+    /// If the channel was closed, then it is recorded in the channel history and the
+    /// channel close callback is sent.
+    ///
+    /// The ordering, version and encoding of an open channel are also refreshed, since a
+    /// completed channel upgrade may have changed them.
+    ///
+    /// With the [`IcaBackend::ControllerModule`] backend, this also completes the channel
+    /// open handshake, since the contract does not receive the `OpenAck`.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn sync_channel(
+        mut deps: DepsMut,
+        env: Env,
+        ica_id: u64,
+    ) -> Result<Response, ContractError> {
+        let mut channel_state = state::CHANNEL_STATE.load(deps.storage, ica_id)?;
+        let channel_id = channel_state.channel.endpoint.channel_id.clone();
 
-        let msg = InstantiateMsg {
-            owner: None,
-            channel_open_init_options: channel_open_init_options.clone(),
-            send_callbacks_to: None,
-        };
+        let (status, ordering, version) = query_channel(
+            &deps.querier,
+            &channel_state.channel.endpoint.port_id,
+            &channel_id,
+        )?;
 
-        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let is_opening = matches!(
+            channel_state.channel_status,
+            state::ChannelStatus::Init | state::ChannelStatus::TryOpen
+        );
+        let is_upgraded = !is_opening
+            && status == state::ChannelStatus::Open
+            && (ordering
+                .as_ref()
+                .is_some_and(|ordering| ordering != &channel_state.channel.order)
+                || version != channel_state.channel.version);
+        if status == channel_state.channel_status && !is_upgraded {
+            return Ok(Response::default());
+        }
 
-        // Ensure that the channel open init options are saved correctly
-        assert_eq!(
-            state::CHANNEL_OPEN_INIT_OPTIONS
-                .load(deps.as_ref().storage)
-                .unwrap(),
-            channel_open_init_options
+        let event = events::channel_sync::status_changed(
+            ica_id,
+            &channel_id,
+            &channel_state.channel_status,
+            &status,
         );
 
-        // Ensure the contract is instantiated successfully
-        assert_eq!(1, res.messages.len());
+        if is_opening
+            && status == state::ChannelStatus::Open
+            && state::STATE.load(deps.storage)?.backend == IcaBackend::ControllerModule
+        {
+            let callback_msg = ibc_channel_open::complete(
+                deps.branch(),
+                &env,
+                ica_id,
+                channel_state.channel,
+                version,
+            )?;
+            let retries = scheduled_retry_submsgs(deps.storage, &env, ica_id)?;
+            return Ok(Response::new()
+                .add_event(event)
+                .add_messages(callback_msg)
+                .add_submessages(retries));
+        }
 
-        let expected_msg = new_ica_channel_open_init_cosmos_msg(
-            env.contract.address.to_string(),
-            channel_open_init_options.connection_id,
-            channel_open_init_options.counterparty_port_id,
-            channel_open_init_options.counterparty_connection_id,
-            None,
+        let mut response = Response::new();
+        if status != channel_state.channel_status {
+            response = response.add_event(event);
+        }
+
+        if is_upgraded {
+            if let Ok(metadata) = serde_json_wasm::from_str::<IcaMetadata>(&version) {
+                let mut ica_info = state::load_ica_info(deps.storage, ica_id)?;
+                ica_info.encoding = metadata.encoding;
+                state::ICA_INFO.save(deps.storage, ica_id, &ica_info)?;
+            }
+            if let Some(ordering) = ordering {
+                channel_state.channel.order = ordering;
+            }
+            channel_state.channel.version = version;
+        }
+
+        channel_state.channel_status = status;
+        state::CHANNEL_STATE.save(deps.storage, ica_id, &channel_state)?;
+
+        if channel_state.channel_status == state::ChannelStatus::Closed {
+            state::close_channel_record(
+                deps.storage,
+                ica_id,
+                &channel_id,
+                env.block.height,
+                state::ChannelCloseReason::Synced,
+            )?;
+            response = response.add_submessages(channel_close_callback(
+                deps.storage,
+                ica_id,
+                channel_state.channel,
+                state::ChannelCloseReason::Synced,
+            )?);
+        }
+
+        Ok(response)
+    }
+
+    /// Sends a query for the ICA host's params, so that the host's `allow_messages` is cached
+    /// once the acknowledgement is received.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn refresh_host_allow_messages(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        ica_id: u64,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+        let ica_info = state::load_ica_info(deps.storage, ica_id)?;
+
+        // The host's allow list is not validated here so that a stale cache can always be refreshed.
+        #[allow(deprecated)]
+        let params_query = QueryRequest::Stargate {
+            path: query_msg::constants::ICA_HOST_PARAMS.to_string(),
+            data: Binary::default(),
+        };
+        let (ica_packet, query_paths) = IcaPacketData::from_cosmos_msgs_with_query_paths(
+            vec![],
+            vec![params_query],
+            &ica_info.encoding,
+            None,
+            &ica_info.ica_address,
+        )?;
+        let timeout = packet_timeout(deps.storage, &env, None, None)?;
+
+        let send_packet_submsg = send_packet_submsg(
+            deps.storage,
+            &env,
+            &info.sender,
+            ica_id,
+            ica_info.channel_id,
+            &ica_packet,
+            &query_paths,
+            timeout,
+            None,
+            vec![],
+            None,
+        )?;
+
+        Ok(Response::default().add_submessage(send_packet_submsg))
+    }
+
+    /// Registers a recurring query subscription.
+    #[allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
+    pub fn register_query_subscription(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        ica_id: u64,
+        queries: Vec<QueryRequest<Empty>>,
+        interval_seconds: u64,
+        cache_results: bool,
+        bounty: Option<Coin>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+        if queries.is_empty() {
+            return Err(ContractError::InvalidQuerySubscription(
+                "queries cannot be empty".to_string(),
+            ));
+        }
+        if interval_seconds == 0 {
+            return Err(ContractError::InvalidQuerySubscription(
+                "interval_seconds must be greater than zero".to_string(),
+            ));
+        }
+        if bounty.as_ref().is_some_and(|coin| coin.amount.is_zero()) {
+            return Err(ContractError::InvalidQuerySubscription(
+                "bounty cannot be zero".to_string(),
+            ));
+        }
+        // Unsupported queries are rejected here so that they never reach the crank
+        for query in &queries {
+            query_msg::query_to_protobuf(query.clone())?;
+        }
+
+        let subscription_id = state::NEXT_SUBSCRIPTION_ID
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+        state::NEXT_SUBSCRIPTION_ID.save(deps.storage, &(subscription_id + 1))?;
+
+        let subscription = Subscription {
+            ica_id,
+            queries,
+            interval_seconds,
+            next_run: env.block.time,
+            cache_results,
+            bounty,
+        };
+        state::SUBSCRIPTIONS.save(deps.storage, subscription_id, &subscription)?;
+        state::SUBSCRIPTION_SCHEDULE.save(
+            deps.storage,
+            (subscription.next_run.nanos(), subscription_id),
+            &Empty {},
+        )?;
+
+        Ok(Response::default().add_attribute("subscription_id", subscription_id.to_string()))
+    }
+
+    /// Removes a query subscription and its cached result.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn cancel_query_subscription(
+        deps: DepsMut,
+        info: MessageInfo,
+        subscription_id: u64,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+        let subscription = state::SUBSCRIPTIONS
+            .may_load(deps.storage, subscription_id)?
+            .ok_or(ContractError::QuerySubscriptionNotFound(subscription_id))?;
+
+        state::SUBSCRIPTIONS.remove(deps.storage, subscription_id);
+        state::SUBSCRIPTION_SCHEDULE.remove(
+            deps.storage,
+            (subscription.next_run.nanos(), subscription_id),
+        );
+        state::SUBSCRIPTION_RESULTS.remove(deps.storage, subscription_id);
+
+        Ok(Response::default())
+    }
+
+    /// Sends a query packet for each due query subscription, and pays their bounties to the sender.
+    /// Can be called by anyone.
+    ///
+    /// The due subscriptions are read from the [`state::SUBSCRIPTION_SCHEDULE`], so the cost does
+    /// not depend on the total number of subscriptions. A subscription whose packet cannot be sent,
+    /// or whose bounty cannot be paid from the contract's balance, is skipped with an event and
+    /// rescheduled, so that it does not block the others.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn crank(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        let limit = limit.unwrap_or(DEFAULT_CRANK_LIMIT) as usize;
+
+        let max = Bound::inclusive((env.block.time.nanos(), u64::MAX));
+        let due = state::SUBSCRIPTION_SCHEDULE
+            .keys(deps.storage, None, Some(max), Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        if due.is_empty() {
+            return Err(ContractError::NoDueQuerySubscriptions);
+        }
+
+        let mut response = Response::default();
+        let mut bounties: Vec<Coin> = vec![];
+        for (next_run, subscription_id) in due {
+            let mut subscription = state::SUBSCRIPTIONS.load(deps.storage, subscription_id)?;
+            subscription.next_run = env.block.time.plus_seconds(subscription.interval_seconds);
+            state::SUBSCRIPTIONS.save(deps.storage, subscription_id, &subscription)?;
+            state::SUBSCRIPTION_SCHEDULE.remove(deps.storage, (next_run, subscription_id));
+            state::SUBSCRIPTION_SCHEDULE.save(
+                deps.storage,
+                (subscription.next_run.nanos(), subscription_id),
+                &Empty {},
+            )?;
+
+            if let Some(bounty) = &subscription.bounty {
+                let owed = bounties
+                    .iter()
+                    .find(|coin| coin.denom == bounty.denom)
+                    .map_or(bounty.amount, |coin| {
+                        coin.amount.saturating_add(bounty.amount)
+                    });
+                let balance = deps
+                    .querier
+                    .query_balance(&env.contract.address, &bounty.denom)?;
+                if balance.amount < owed {
+                    response = response.add_event(events::query_subscription::skipped(
+                        subscription_id,
+                        subscription.ica_id,
+                        &ContractError::InsufficientBountyBalance(bounty.to_string()).to_string(),
+                    ));
+                    continue;
+                }
+            }
+
+            let send_packet_submsg = match subscription_submsg(
+                deps.storage,
+                &env,
+                &info.sender,
+                subscription_id,
+                &subscription,
+            ) {
+                Ok(submsg) => submsg,
+                Err(err) => {
+                    response = response.add_event(events::query_subscription::skipped(
+                        subscription_id,
+                        subscription.ica_id,
+                        &err.to_string(),
+                    ));
+                    continue;
+                }
+            };
+            response = response.add_submessage(send_packet_submsg);
+
+            if let Some(bounty) = subscription.bounty {
+                match bounties.iter_mut().find(|coin| coin.denom == bounty.denom) {
+                    Some(coin) => coin.amount += bounty.amount,
+                    None => bounties.push(bounty),
+                }
+            }
+        }
+
+        if !bounties.is_empty() {
+            response = response.add_message(BankMsg::Send {
+                to_address: info.sender.into_string(),
+                amount: bounties,
+            });
+        }
+
+        Ok(response)
+    }
+
+    /// Creates the [`SubMsg`] that sends the queries of a subscription with its ICA.
+    fn subscription_submsg(
+        storage: &mut dyn Storage,
+        env: &Env,
+        sender: &Addr,
+        subscription_id: u64,
+        subscription: &Subscription,
+    ) -> Result<SubMsg, ContractError> {
+        let ica_info = state::load_ica_info(storage, subscription.ica_id)?;
+        let (ica_packet, query_paths) = IcaPacketData::from_cosmos_msgs_with_query_paths(
+            vec![],
+            subscription.queries.clone(),
+            &ica_info.encoding,
+            None,
+            &ica_info.ica_address,
+        )?;
+        assert_allowed_by_host(
+            storage,
+            subscription.ica_id,
+            &ica_packet,
+            &ica_info.encoding,
+        )?;
+        let timeout = packet_timeout(storage, env, None, None)?;
+
+        send_packet_submsg(
+            storage,
+            env,
+            sender,
+            subscription.ica_id,
+            ica_info.channel_id,
+            &ica_packet,
+            &query_paths,
+            timeout,
+            Some(subscription_id),
+            vec![],
+            None,
+        )
+    }
+
+    /// Returns the timeout of a packet from the relative `timeout_seconds` or the absolute
+    /// `timeout` of the message. If neither is given, then the default timeout is used.
+    fn packet_timeout(
+        storage: &dyn Storage,
+        env: &Env,
+        timeout_seconds: Option<u64>,
+        timeout: Option<PacketTimeout>,
+    ) -> Result<IbcTimeout, ContractError> {
+        let timeout = match (timeout_seconds, timeout) {
+            (Some(_), Some(_)) => {
+                return Err(ContractError::InvalidPacketTimeout(
+                    "timeout_seconds and timeout cannot both be set".to_string(),
+                ))
+            }
+            (Some(timeout_seconds), None) => {
+                return Ok(IbcTimeout::with_timestamp(
+                    env.block.time.plus_seconds(timeout_seconds),
+                ))
+            }
+            (None, Some(timeout)) => timeout,
+            (None, None) => {
+                let timeout_seconds = state::DEFAULT_TIMEOUT
+                    .may_load(storage)?
+                    .unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+                return Ok(IbcTimeout::with_timestamp(
+                    env.block.time.plus_seconds(timeout_seconds),
+                ));
+            }
+        };
+
+        // A timeout in the past is rejected here, since the packet could never be received.
+        if timeout
+            .timestamp
+            .is_some_and(|timestamp| timestamp <= env.block.time)
+        {
+            return Err(ContractError::InvalidPacketTimeout(
+                "timestamp must be after the current block time".to_string(),
+            ));
+        }
+        if timeout.block.is_some_and(|block| block.height == 0) {
+            return Err(ContractError::InvalidPacketTimeout(
+                "block height must be greater than zero".to_string(),
+            ));
+        }
+
+        match (timeout.block, timeout.timestamp) {
+            (Some(block), Some(timestamp)) => Ok(IbcTimeout::with_both(block, timestamp)),
+            (Some(block), None) => Ok(IbcTimeout::with_block(block)),
+            (None, Some(timestamp)) => Ok(IbcTimeout::with_timestamp(timestamp)),
+            (None, None) => Err(ContractError::InvalidPacketTimeout(
+                "either timestamp or block must be set".to_string(),
+            )),
+        }
+    }
+
+    /// Returns an error if the sender is neither the contract owner nor an enqueuer.
+    fn assert_enqueuer(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
+        if cw_ownable::is_owner(storage, sender)?
+            || state::ENQUEUERS
+                .may_load(storage)?
+                .is_some_and(|enqueuers| enqueuers.contains(sender))
+        {
+            Ok(())
+        } else {
+            Err(ContractError::Unauthorized)
+        }
+    }
+
+    /// Removes the given entries and the totals of the queue of an ICA.
+    fn remove_queue(storage: &mut dyn Storage, ica_id: u64, entry_ids: &[u64]) {
+        for entry_id in entry_ids {
+            state::QUEUE.remove(storage, (ica_id, *entry_id));
+        }
+        state::QUEUE_INFO.remove(storage, ica_id);
+    }
+
+    /// The packet built from a queue, with its query paths and correlation ids.
+    type QueuedPacket = (IcaPacketData, Vec<(String, bool)>, Vec<Correlation>);
+
+    /// Builds the packet that sends the messages of the queue entries in order, followed by their
+    /// queries in order. Also returns the query paths, and the correlation ids of the entries with
+    /// the indices of their messages and queries in the packet.
+    // The indices cannot overflow since the packet size is limited by `MAX_QUEUED_PACKET_SIZE`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn queued_packet(
+        queue: &[QueueEntry],
+        ica_info: &IcaInfo,
+        packet_memo: Option<String>,
+    ) -> Result<QueuedPacket, ContractError> {
+        let mut messages = vec![];
+        let mut queries = vec![];
+        let mut correlations = Vec::with_capacity(queue.len());
+        for entry in queue {
+            correlations.push(Correlation {
+                sender: entry.sender.clone(),
+                correlation_id: entry.correlation_id.clone(),
+                first_message_index: messages.len() as u32,
+                message_count: entry.messages.len() as u32,
+                first_query_index: queries.len() as u32,
+                query_count: entry.queries.len() as u32,
+            });
+            messages.extend(entry.messages.iter().cloned());
+            queries.extend(entry.queries.iter().cloned());
+        }
+
+        let (ica_packet, query_paths) = IcaPacketData::from_cosmos_msgs_with_query_paths(
+            messages,
+            queries,
+            &ica_info.encoding,
+            packet_memo,
+            &ica_info.ica_address,
+        )?;
+
+        Ok((ica_packet, query_paths, correlations))
+    }
+
+    /// Rejects the messages that the host does not allow, if the host's allow list is cached.
+    fn assert_allowed_by_host(
+        storage: &dyn Storage,
+        ica_id: u64,
+        ica_packet: &IcaPacketData,
+        encoding: &TxEncoding,
+    ) -> Result<(), ContractError> {
+        if let Some(allow_messages) = state::HOST_ALLOW_MESSAGES.may_load(storage, ica_id)? {
+            if !allow_messages.iter().any(|msg| msg == "*") {
+                if let Some(type_url) = ica_packet
+                    .type_urls(encoding)?
+                    .into_iter()
+                    .find(|type_url| !allow_messages.contains(type_url))
+                {
+                    return Err(ContractError::MessageNotAllowedByHost(type_url));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rejects a [`RetryPolicy`] combined with an absolute [`PacketTimeout`], since the retries
+    /// could only be sent after the deadline of the packet.
+    fn assert_retry_timeout(
+        retry: Option<&RetryPolicy>,
+        timeout: Option<&PacketTimeout>,
+    ) -> Result<(), ContractError> {
+        if retry.is_some() && timeout.is_some() {
+            return Err(ContractError::InvalidPacketTimeout(
+                "a retry policy cannot be combined with an absolute timeout".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the retry state of a packet sent with a [`RetryPolicy`].
+    /// The retries use the relative timeout of the packet, or the default timeout.
+    fn packet_retry(
+        storage: &dyn Storage,
+        policy: Option<RetryPolicy>,
+        timeout_seconds: Option<u64>,
+        ica_packet: &IcaPacketData,
+    ) -> StdResult<Option<PacketRetry>> {
+        let Some(policy) = policy else {
+            return Ok(None);
+        };
+        let timeout_seconds = match timeout_seconds {
+            Some(timeout_seconds) => timeout_seconds,
+            None => state::DEFAULT_TIMEOUT
+                .may_load(storage)?
+                .unwrap_or(DEFAULT_TIMEOUT_SECONDS),
+        };
+
+        Ok(Some(PacketRetry {
+            policy,
+            attempts: 0,
+            timeout_seconds,
+            packet_data: ica_packet.clone(),
+        }))
+    }
+
+    /// Update the ownership of the contract.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_ownership(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        action: cw_ownable::Action,
+    ) -> Result<Response, ContractError> {
+        if action == cw_ownable::Action::RenounceOwnership {
+            return Err(ContractError::OwnershipCannotBeRenounced);
+        }
+
+        cw_ownable::update_ownership(deps, &env.block, &info.sender, action)?;
+
+        Ok(Response::default())
+    }
+
+    /// Updates the callback address.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn update_callback_address(
+        deps: DepsMut,
+        info: MessageInfo,
+        callback_address: Option<String>,
+    ) -> Result<Response, ContractError> {
+        cw_ownable::assert_owner(deps.storage, &info.sender)?;
+
+        let mut contract_state = state::STATE.load(deps.storage)?;
+
+        contract_state.callback_address = callback_address
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+
+        state::STATE.save(deps.storage, &contract_state)?;
+
+        Ok(Response::default())
+    }
+}
+
+mod reply {
+    use cosmwasm_std::{
+        from_json, Binary, IbcChannel, IbcEndpoint, IbcOrder, SubMsgResponse, SubMsgResult,
+    };
+
+    use crate::ibc::{
+        relay::timeout_callback_msg,
+        types::{events, stargate::controller},
+    };
+    use crate::types::state::packet::PendingPacket;
+
+    use super::{state, ChannelState, ContractError, DepsMut, Env, Response};
+
+    /// Handles the failed [`OnChannelCloseCallback`](crate::types::callbacks::IcaControllerCallbackMsg::OnChannelCloseCallback).
+    /// The error is reported in an attribute and otherwise ignored, so the channel stays closed.
+    pub fn channel_close_callback(result: &SubMsgResult) -> Response {
+        match result {
+            SubMsgResult::Err(err) => {
+                Response::default().add_attribute("channel_close_callback_error", err)
+            }
+            SubMsgResult::Ok(_) => Response::default(),
+        }
+    }
+
+    /// Handles the reply to the `MsgRegisterInterchainAccount` of the controller module backend.
+    /// The payload is the `ica_id`.
+    ///
+    /// The channel is saved in the `STATE_INIT` status, and it is completed by
+    /// [`ExecuteMsg::SyncChannel`](crate::types::msg::ExecuteMsg::SyncChannel) once the
+    /// controller module finishes the handshake.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn register_interchain_account(
+        deps: DepsMut,
+        env: Env,
+        payload: Binary,
+        result: SubMsgResult,
+    ) -> Result<Response, ContractError> {
+        let resp = match result {
+            SubMsgResult::Ok(resp) => resp,
+            SubMsgResult::Err(err) => {
+                return Err(ContractError::RegisterInterchainAccountFailed(err))
+            }
+        };
+
+        let ica_id: u64 = from_json(payload)?;
+        let (channel_id, port_id) =
+            controller::register_interchain_account_response(&msg_response_data(&resp))?;
+        let port_id = if port_id.is_empty() {
+            format!(
+                "{}{}",
+                controller::CONTROLLER_PORT_PREFIX,
+                env.contract.address
+            )
+        } else {
+            port_id
+        };
+
+        let options = state::CHANNEL_OPEN_INIT_OPTIONS.load(deps.storage, ica_id)?;
+        let channel = IbcChannel::new(
+            IbcEndpoint {
+                port_id,
+                channel_id: channel_id.clone(),
+            },
+            IbcEndpoint {
+                port_id: options.counterparty_port_id(),
+                channel_id: String::new(),
+            },
+            options.channel_ordering.unwrap_or(IbcOrder::Ordered),
+            String::new(),
+            options.connection_id,
+        );
+
+        state::CHANNEL_ICA_ID.save(deps.storage, &channel_id, &ica_id)?;
+        state::CHANNEL_STATE.save(
+            deps.storage,
+            ica_id,
+            &ChannelState {
+                channel,
+                channel_status: state::ChannelStatus::Init,
+            },
+        )?;
+
+        Ok(Response::default())
+    }
+
+    /// Handles the reply to an ICA packet, and records it in the pending packets.
+    /// The payload is the id under which the packet is stored in [`state::QUERY`].
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn send_packet(
+        deps: DepsMut,
+        _env: Env,
+        payload: Binary,
+        result: SubMsgResult,
+    ) -> Result<Response, ContractError> {
+        match result {
+            SubMsgResult::Ok(resp) => {
+                let query_id: u64 = from_json(payload)?;
+                let sequence = send_packet_sequence(&resp)?;
+                let query_packet = state::QUERY.load(deps.storage, query_id)?;
+                let key = (query_packet.channel_id.as_str(), sequence);
+
+                state::QUERY.remove(deps.storage, query_id);
+                state::PENDING_PACKETS.save(
+                    deps.storage,
+                    key,
+                    &PendingPacket {
+                        channel_id: query_packet.channel_id.clone(),
+                        sequence,
+                        ..query_packet.packet
+                    },
+                )?;
+                if !query_packet.paths.is_empty() {
+                    state::PENDING_QUERIES.save(deps.storage, key, &query_packet.paths)?;
+                }
+                if !query_packet.correlations.is_empty() {
+                    state::PENDING_CORRELATIONS.save(
+                        deps.storage,
+                        key,
+                        &query_packet.correlations,
+                    )?;
+                }
+
+                if let Some(retry) = &query_packet.retry {
+                    state::PACKET_RETRIES.save(deps.storage, key, retry)?;
+                }
+
+                if let Some(subscription_id) = query_packet.subscription_id {
+                    state::PENDING_SUBSCRIPTION_PACKETS.save(
+                        deps.storage,
+                        key,
+                        &subscription_id,
+                    )?;
+                }
+
+                Ok(Response::default())
+            }
+            SubMsgResult::Err(err) => Err(ContractError::SendPacketFailed(err)),
+        }
+    }
+
+    /// Handles the reply to a packet that is resent by its retry policy. A successful resend is
+    /// recorded like [`send_packet`]. If the resend failed, the failed retry is reported in an
+    /// event, and the timeout callback of the packet that timed out is sent.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn resend_packet(
+        deps: DepsMut,
+        env: Env,
+        payload: Binary,
+        result: SubMsgResult,
+    ) -> Result<Response, ContractError> {
+        let query_id: u64 = from_json(&payload)?;
+        let timed_out = state::RESENT_PACKETS.load(deps.storage, query_id)?;
+        state::RESENT_PACKETS.remove(deps.storage, query_id);
+
+        let err = match result {
+            SubMsgResult::Ok(resp) => {
+                return send_packet(deps, env, payload, SubMsgResult::Ok(resp))
+            }
+            SubMsgResult::Err(err) => err,
+        };
+
+        let query_packet = state::QUERY.load(deps.storage, query_id)?;
+        state::QUERY.remove(deps.storage, query_id);
+
+        let ica_id = query_packet.packet.ica_id;
+        let attempt = query_packet.retry.map_or(0, |retry| retry.attempts);
+        let event = events::packet_retry::failed(ica_id, &timed_out.packet, attempt, &err);
+        // The failed attempt was never sent, so it is not counted in the retries.
+        let callback_msg = timeout_callback_msg(
+            deps.storage,
+            ica_id,
+            timed_out,
+            query_packet.correlations,
+            attempt.saturating_sub(1),
+        )?;
+
+        Ok(Response::default()
+            .add_event(event)
+            .add_messages(callback_msg))
+    }
+
+    /// Decodes the sequence from the `MsgSendPacketResponse`, or from the `MsgSendTxResponse`
+    /// of the controller module backend.
+    fn send_packet_sequence(resp: &SubMsgResponse) -> Result<u64, ContractError> {
+        anybuf::Bufany::deserialize(&msg_response_data(resp))?
+            .uint64(1)
+            .ok_or(ContractError::SendPacketSequenceNotFound)
+    }
+
+    /// Returns the data of the first message response.
+    /// Falls back to the deprecated `data` field if the chain does not populate `msg_responses`.
+    fn msg_response_data(resp: &SubMsgResponse) -> Binary {
+        #[allow(deprecated)]
+        resp.msg_responses
+            .first()
+            .map(|msg_resp| msg_resp.value.clone())
+            .or_else(|| resp.data.clone())
+            .unwrap_or_default()
+    }
+}
+
+mod query {
+    use cosmwasm_std::{Addr, Order};
+    use cw_storage_plus::Bound;
+
+    use crate::ibc::types::packet::DEFAULT_TIMEOUT_SECONDS;
+    use crate::types::{
+        msg::{
+            options::AutoReopenPolicy, DEFAULT_PACKET_HISTORY_RETENTION, DEFAULT_PAGE_LIMIT,
+            MAX_PAGE_LIMIT,
+        },
+        state::{
+            ica_query::{Subscription, SubscriptionResult},
+            packet::{PacketOutcome, PacketStatus, PendingPacket},
+            queue::QueueEntry,
+            ChannelRecord, IcaInfo,
+        },
+    };
+
+    use super::{state, ChannelState, ContractState, Deps, StdResult};
+
+    /// Returns the saved contract state.
+    pub fn state(deps: Deps) -> StdResult<ContractState> {
+        state::STATE.load(deps.storage)
+    }
+
+    /// Returns the saved channel state of the ICA if it exists.
+    pub fn channel(deps: Deps, ica_id: u64) -> StdResult<ChannelState> {
+        state::CHANNEL_STATE.load(deps.storage, ica_id)
+    }
+
+    /// Returns the saved ICA info if it exists.
+    pub fn ica_info(deps: Deps, ica_id: u64) -> StdResult<IcaInfo> {
+        state::ICA_INFO.load(deps.storage, ica_id)
+    }
+
+    /// Returns the allowed host ports other than the default host port.
+    pub fn allowed_host_ports(deps: Deps) -> StdResult<Vec<String>> {
+        Ok(state::ALLOWED_HOST_PORTS
+            .may_load(deps.storage)?
+            .unwrap_or_default())
+    }
+
+    /// Returns the auto reopen policy of the ICA if it exists.
+    pub fn auto_reopen_policy(deps: Deps, ica_id: u64) -> StdResult<Option<AutoReopenPolicy>> {
+        state::AUTO_REOPEN_POLICY.may_load(deps.storage, ica_id)
+    }
+
+    /// Returns the timeout in seconds of the packets that are sent without a timeout.
+    pub fn default_timeout(deps: Deps) -> StdResult<u64> {
+        Ok(state::DEFAULT_TIMEOUT
+            .may_load(deps.storage)?
+            .unwrap_or(DEFAULT_TIMEOUT_SECONDS))
+    }
+
+    /// Returns the addresses that are allowed to enqueue messages other than the owner.
+    pub fn enqueuers(deps: Deps) -> StdResult<Vec<Addr>> {
+        Ok(state::ENQUEUERS.may_load(deps.storage)?.unwrap_or_default())
+    }
+
+    /// Returns the queued entries of the ICA.
+    pub fn queue(deps: Deps, ica_id: u64) -> StdResult<Vec<QueueEntry>> {
+        state::QUEUE
+            .prefix(ica_id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(_, entry)| entry))
+            .collect()
+    }
+
+    /// Returns the cached `allow_messages` of the ICA host if it exists.
+    pub fn host_allow_messages(deps: Deps, ica_id: u64) -> StdResult<Option<Vec<String>>> {
+        state::HOST_ALLOW_MESSAGES.may_load(deps.storage, ica_id)
+    }
+
+    /// Returns a page of the channel history of the ICA.
+    pub fn channel_history(
+        deps: Deps,
+        ica_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<(u64, ChannelRecord)>> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+        state::CHANNEL_HISTORY
+            .prefix(ica_id)
+            .range(
+                deps.storage,
+                start_after.map(Bound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .take(limit)
+            .collect()
+    }
+
+    /// Returns a page of the pending packets of all the ICAs.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn pending_packets(
+        deps: Deps,
+        start_after: Option<(String, u64)>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<PendingPacket>> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+        let start = start_after
+            .as_ref()
+            .map(|(channel_id, sequence)| Bound::exclusive((channel_id.as_str(), *sequence)));
+        state::PENDING_PACKETS
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, packet)| packet))
+            .collect()
+    }
+
+    /// Returns the pending packet with the given sequence if it exists.
+    /// The channel defaults to the current channel of the ICA.
+    pub fn packet(
+        deps: Deps,
+        ica_id: u64,
+        sequence: u64,
+        channel_id: Option<String>,
+    ) -> StdResult<Option<PendingPacket>> {
+        let channel_id = match channel_id {
+            Some(channel_id) => channel_id,
+            None => match state::ICA_INFO.may_load(deps.storage, ica_id)? {
+                Some(ica_info) => ica_info.channel_id,
+                None => return Ok(None),
+            },
+        };
+
+        state::PENDING_PACKETS.may_load(deps.storage, (&channel_id, sequence))
+    }
+
+    /// Returns a page of the packet history, optionally filtered by status.
+    /// The filtered pages are read from the [`state::PACKET_HISTORY_BY_STATUS`] index, so that at
+    /// most `limit` outcomes are loaded.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn packet_history(
+        deps: Deps,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        status: Option<PacketStatus>,
+    ) -> StdResult<Vec<(u64, PacketOutcome)>> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+        let start = start_after.map(Bound::exclusive);
+        let Some(status) = status else {
+            return state::PACKET_HISTORY
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .collect();
+        };
+
+        state::PACKET_HISTORY_BY_STATUS
+            .prefix(status.as_str())
+            .keys(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|index| {
+                let index = index?;
+                Ok((index, state::PACKET_HISTORY.load(deps.storage, index)?))
+            })
+            .collect()
+    }
+
+    /// Returns the retention limit of the packet history.
+    pub fn packet_history_retention(deps: Deps) -> StdResult<u64> {
+        Ok(state::PACKET_HISTORY_RETENTION
+            .may_load(deps.storage)?
+            .unwrap_or(DEFAULT_PACKET_HISTORY_RETENTION))
+    }
+
+    /// Returns the query subscription with the given id.
+    pub fn query_subscription(deps: Deps, subscription_id: u64) -> StdResult<Subscription> {
+        state::SUBSCRIPTIONS.load(deps.storage, subscription_id)
+    }
+
+    /// Returns the latest cached result of the query subscription if it exists.
+    pub fn query_subscription_result(
+        deps: Deps,
+        subscription_id: u64,
+    ) -> StdResult<Option<SubscriptionResult>> {
+        state::SUBSCRIPTION_RESULTS.may_load(deps.storage, subscription_id)
+    }
+}
+
+mod migrate {
+    use cosmwasm_std::Storage;
+
+    use super::{keys, state, ContractError, ContractState, Deps};
+
+    /// Validate that the contract version is semver compliant
+    /// and greater than the previous version.
+    pub fn validate_semver(deps: Deps) -> Result<(), ContractError> {
+        let prev_cw2_version = cw2::get_contract_version(deps.storage)?;
+        if prev_cw2_version.contract != keys::CONTRACT_NAME {
+            return Err(ContractError::InvalidMigrationVersion {
+                expected: keys::CONTRACT_NAME.to_string(),
+                actual: prev_cw2_version.contract,
+            });
+        }
+
+        let version: semver::Version = keys::CONTRACT_VERSION.parse()?;
+        let prev_version: semver::Version = prev_cw2_version.version.parse()?;
+        if prev_version >= version {
+            return Err(ContractError::InvalidMigrationVersion {
+                expected: format!("> {prev_version}"),
+                actual: keys::CONTRACT_VERSION.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Moves the state of the only ICA of a contract that predates multiple ICAs to `ica_id` 0.
+    /// Does nothing if the state was already migrated.
+    pub fn single_ica_state(storage: &mut dyn Storage) -> Result<(), ContractError> {
+        let Some(options) = state::legacy::CHANNEL_OPEN_INIT_OPTIONS.may_load(storage)? else {
+            return Ok(());
+        };
+        state::legacy::CHANNEL_OPEN_INIT_OPTIONS.remove(storage);
+        state::MIGRATED_SINGLE_ICA.save(storage, &true)?;
+        state::CONNECTION_ICA_ID.save(storage, &options.connection_id, &0)?;
+        state::CHANNEL_OPEN_INIT_OPTIONS.save(storage, 0, &options)?;
+
+        let legacy_state = state::legacy::STATE.load(storage)?;
+        if let Some(ica_info) = legacy_state.ica_info {
+            state::ICA_INFO.save(storage, 0, &ica_info)?;
+        }
+        state::STATE.save(storage, &ContractState::new(legacy_state.callback_address))?;
+
+        if let Some(channel_state) = state::legacy::CHANNEL_STATE.may_load(storage)? {
+            state::legacy::CHANNEL_STATE.remove(storage);
+            state::CHANNEL_ICA_ID.save(storage, &channel_state.channel.endpoint.channel_id, &0)?;
+            state::CHANNEL_STATE.save(storage, 0, &channel_state)?;
+        }
+
+        if let Some(allow_messages) = state::legacy::HOST_ALLOW_MESSAGES.may_load(storage)? {
+            state::legacy::HOST_ALLOW_MESSAGES.remove(storage);
+            state::HOST_ALLOW_MESSAGES.save(storage, 0, &allow_messages)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ibc::types::stargate::channel::new_ica_channel_open_init_cosmos_msg;
+    use crate::types::msg::options::ChannelOpenInitOptions;
+
+    use super::*;
+    use cosmwasm_std::testing::{
+        message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{
+        from_json, Api, ContractResult, Empty, GrpcQuery, OwnedDeps, Querier, QuerierResult,
+        QueryRequest, SubMsg, SystemResult,
+    };
+
+    /// A querier that answers the gRPC queries with the given path with a fixed response.
+    struct GrpcQuerier {
+        base: MockQuerier,
+        path: String,
+        response: Binary,
+    }
+
+    impl Querier for GrpcQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            match from_json::<QueryRequest<Empty>>(bin_request) {
+                Ok(QueryRequest::Grpc(GrpcQuery { path, .. })) if path == self.path => {
+                    SystemResult::Ok(ContractResult::Ok(self.response.clone()))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    fn mock_dependencies_with_grpc(
+        path: &str,
+        response: Vec<u8>,
+    ) -> OwnedDeps<MockStorage, MockApi, GrpcQuerier> {
+        OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: GrpcQuerier {
+                base: MockQuerier::default(),
+                path: path.to_string(),
+                response: Binary::new(response),
+            },
+            custom_query_type: std::marker::PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        let msg = InstantiateMsg {
+            owner: None,
+            channel_open_init_options: channel_open_init_options.clone(),
+            send_callbacks_to: None,
+            backend: None,
+            allowed_host_ports: None,
+        };
+
+        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // Ensure that the channel open init options are saved correctly
+        assert_eq!(
+            state::CHANNEL_OPEN_INIT_OPTIONS
+                .load(deps.as_ref().storage, 0)
+                .unwrap(),
+            channel_open_init_options
+        );
+
+        // Ensure the contract is instantiated successfully
+        assert_eq!(1, res.messages.len());
+
+        let tx_encoding = channel_open_init_options.proposed_tx_encoding();
+        let expected_msg = new_ica_channel_open_init_cosmos_msg(
+            env.contract.address.to_string(),
+            channel_open_init_options.connection_id,
+            channel_open_init_options.counterparty_port_id,
+            channel_open_init_options.counterparty_connection_id,
+            Some(tx_encoding),
             channel_open_init_options.channel_ordering,
         );
-        assert_eq!(res.messages[0], SubMsg::new(expected_msg));
+        assert_eq!(res.messages[0], SubMsg::new(expected_msg));
+
+        // Ensure the admin is saved correctly
+        let owner = cw_ownable::get_ownership(&deps.storage)
+            .unwrap()
+            .owner
+            .unwrap();
+        assert_eq!(owner, info.sender);
+
+        // Ensure that the contract name and version are saved correctly
+        let contract_version = cw2::get_contract_version(&deps.storage).unwrap();
+        assert_eq!(contract_version.contract, keys::CONTRACT_NAME);
+        assert_eq!(contract_version.version, keys::CONTRACT_VERSION);
+    }
+
+    #[test]
+    fn test_instantiate_with_custom_host_port() {
+        let mut deps = mock_dependencies();
+
+        let info = message_info(&deps.api.addr_make("creator"), &[]);
+        let env = mock_env();
+
+        let msg = |allowed_host_ports: Vec<&str>| InstantiateMsg {
+            owner: None,
+            channel_open_init_options: ChannelOpenInitOptions {
+                connection_id: "connection-0".to_string(),
+                counterparty_connection_id: "connection-1".to_string(),
+                counterparty_port_id: Some("wasm.host".to_string()),
+                channel_ordering: None,
+                expected_host_chain_id: None,
+                expected_address_prefix: None,
+                tx_encodings: vec![],
+            },
+            send_callbacks_to: None,
+            backend: None,
+            allowed_host_ports: Some(allowed_host_ports.into_iter().map(String::from).collect()),
+        };
+
+        // Invalid port ids are rejected
+        let err =
+            instantiate(deps.as_mut(), env.clone(), info.clone(), msg(vec!["a"])).unwrap_err();
+        assert_eq!(err.to_string(), ContractError::InvalidHostPort.to_string());
+
+        // The port is allowed before the channel is opened
+        instantiate(deps.as_mut(), env, info, msg(vec!["wasm.host"])).unwrap();
+        assert!(state::is_host_port_allowed(&deps.storage, "wasm.host").unwrap());
+    }
+
+    #[test]
+    fn test_update_callback_address() {
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        // Instantiate the contract
+        let _res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options,
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure the contract admin can update the callback address
+        let new_callback_address = deps.api.addr_make("new_callback_address").to_string();
+        let msg = ExecuteMsg::UpdateCallbackAddress {
+            callback_address: Some(new_callback_address.clone()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        assert_eq!(0, res.messages.len());
+
+        let state = state::STATE.load(&deps.storage).unwrap();
+        assert_eq!(
+            state.callback_address,
+            Some(deps.api.addr_validate(&new_callback_address).unwrap())
+        );
+
+        // Ensure a non-admin cannot update the callback address
+        let non_admin = deps.api.addr_make("non-admin");
+        let info = message_info(&non_admin, &[]);
+        let msg = ExecuteMsg::UpdateCallbackAddress {
+            callback_address: Some("new_callback_address".to_string()),
+        };
+
+        let res = execute(deps.as_mut(), env, info, msg);
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Caller is not the contract's current owner".to_string()
+        );
+    }
+
+    #[test]
+    fn test_create_channel_for_multiple_icas() {
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        // Instantiate the contract, which opens the channel of ica 0
+        let _res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options: channel_open_init_options.clone(),
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+
+        // A new ica needs its own options
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::CreateChannel {
+                channel_open_init_options: None,
+                ica_id: 1,
+                allow_new_ica_address: false,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::NoChannelInitOptions.to_string()
+        );
+
+        // A connection cannot be shared by two icas
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::CreateChannel {
+                channel_open_init_options: Some(channel_open_init_options),
+                ica_id: 1,
+                allow_new_ica_address: false,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::ConnectionUsedByOtherIca {
+                connection_id: "connection-0".to_string(),
+                ica_id: 0,
+            }
+            .to_string()
+        );
+
+        let other_options = ChannelOpenInitOptions {
+            connection_id: "connection-2".to_string(),
+            counterparty_connection_id: "connection-3".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+        let res = execute(
+            deps.as_mut(),
+            env,
+            info,
+            ExecuteMsg::CreateChannel {
+                channel_open_init_options: Some(other_options.clone()),
+                ica_id: 1,
+                allow_new_ica_address: false,
+            },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        assert_eq!(
+            state::CHANNEL_OPEN_INIT_OPTIONS
+                .load(&deps.storage, 1)
+                .unwrap(),
+            other_options
+        );
+        assert_eq!(
+            state::CONNECTION_ICA_ID
+                .load(&deps.storage, "connection-2")
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_multiple_query_packets() {
+        use cosmwasm_std::{
+            BankQuery, Binary, MsgResponse, QueryRequest, SubMsgResponse, SubMsgResult,
+        };
+
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        // Instantiate the contract
+        let _res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options,
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+
+        // Set the ica info as if the handshake was completed
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new(
+                    "ica_address",
+                    "channel-0",
+                    crate::ibc::types::metadata::TxEncoding::Protobuf,
+                ),
+            )
+            .unwrap();
+
+        // Send two query packets as if they were in the same transaction
+        let mut payloads = vec![];
+        for denom in ["uatom", "uosmo"] {
+            let msg = ExecuteMsg::SendCosmosMsgs {
+                ica_id: 0,
+                messages: vec![],
+                queries: vec![QueryRequest::Bank(BankQuery::Balance {
+                    address: "ica_address".to_string(),
+                    denom: denom.to_string(),
+                })],
+                packet_memo: None,
+                timeout_seconds: None,
+                timeout: None,
+                retry: None,
+            };
+            let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+            assert_eq!(1, res.messages.len());
+            assert_eq!(res.messages[0].id, keys::reply_ids::SEND_PACKET);
+            payloads.push(res.messages[0].payload.clone());
+        }
+        assert_ne!(payloads[0], payloads[1]);
+
+        // Reply in reverse order with different sequences
+        for (payload, sequence) in payloads.into_iter().rev().zip([1_u64, 2]) {
+            #[allow(deprecated)]
+            let result = SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+                msg_responses: vec![MsgResponse {
+                    type_url: "/ibc.core.channel.v1.MsgSendPacketResponse".to_string(),
+                    value: Binary::new(anybuf::Anybuf::new().append_uint64(1, sequence).into_vec()),
+                }],
+            });
+            let reply_msg = Reply {
+                id: keys::reply_ids::SEND_PACKET,
+                payload,
+                gas_used: 0,
+                result,
+            };
+            reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        }
+
+        let paths_1 = state::PENDING_QUERIES
+            .load(&deps.storage, ("channel-0", 1))
+            .unwrap();
+        let paths_2 = state::PENDING_QUERIES
+            .load(&deps.storage, ("channel-0", 2))
+            .unwrap();
+        assert_eq!(paths_1.len(), 1);
+        assert_eq!(paths_1, paths_2);
+
+        // All the query paths are moved to the pending queries
+        assert!(state::QUERY.is_empty(&deps.storage));
+
+        // A failed submessage returns an error instead of panicking
+        let reply_msg = Reply {
+            id: keys::reply_ids::SEND_PACKET,
+            payload: to_json_binary(&0_u64).unwrap(),
+            gas_used: 0,
+            result: SubMsgResult::Err("error".to_string()),
+        };
+        let err = reply(deps.as_mut(), env, reply_msg).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::SendPacketFailed("error".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_channel_history() {
+        use cosmwasm_std::{from_json, IbcOrder};
+
+        use crate::types::state::{ChannelCloseReason, ChannelRecord};
+
+        let mut deps = mock_dependencies();
+
+        let record = |channel_id: &str| ChannelRecord {
+            channel_id: channel_id.to_string(),
+            ica_address: "ica_address".to_string(),
+            ordering: IbcOrder::Ordered,
+            version: "ics27-1".to_string(),
+            open_height: 10,
+            close_height: None,
+            close_reason: None,
+        };
+
+        state::push_channel_record(&mut deps.storage, 0, &record("channel-0")).unwrap();
+        state::close_channel_record(
+            &mut deps.storage,
+            0,
+            "channel-0",
+            20,
+            ChannelCloseReason::Timeout,
+        )
+        .unwrap();
+        state::push_channel_record(&mut deps.storage, 0, &record("channel-1")).unwrap();
+        state::push_channel_record(&mut deps.storage, 1, &record("channel-2")).unwrap();
+
+        // Closing a channel that is not the last one of the ICA does not change the history
+        state::close_channel_record(
+            &mut deps.storage,
+            0,
+            "channel-0",
+            30,
+            ChannelCloseReason::CloseInit,
+        )
+        .unwrap();
+
+        let history_query = |start_after, limit| QueryMsg::GetChannelHistory {
+            ica_id: 0,
+            start_after,
+            limit,
+        };
+
+        let res = query(deps.as_ref(), mock_env(), history_query(None, None)).unwrap();
+        let history: Vec<(u64, ChannelRecord)> = from_json(res).unwrap();
+        assert_eq!(
+            history,
+            vec![
+                (
+                    0,
+                    ChannelRecord {
+                        close_height: Some(20),
+                        close_reason: Some(ChannelCloseReason::Timeout),
+                        ..record("channel-0")
+                    }
+                ),
+                (1, record("channel-1")),
+            ]
+        );
+
+        let res = query(deps.as_ref(), mock_env(), history_query(None, Some(1))).unwrap();
+        let history: Vec<(u64, ChannelRecord)> = from_json(res).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].1.channel_id, "channel-0");
+
+        let res = query(deps.as_ref(), mock_env(), history_query(Some(0), None)).unwrap();
+        let history: Vec<(u64, ChannelRecord)> = from_json(res).unwrap();
+        assert_eq!(history, vec![(1, record("channel-1"))]);
+    }
+
+    #[test]
+    fn test_sync_channel() {
+        use cosmos_sdk_proto::{
+            ibc::core::channel::v1::{Channel, QueryChannelResponse, State},
+            traits::Message,
+        };
+        use cosmwasm_std::{testing::mock_ibc_channel, IbcOrder};
+
+        use crate::ibc::types::stargate::channel::CHANNEL_PATH;
+        use crate::types::state::{ChannelCloseReason, ChannelRecord, ChannelStatus};
+
+        let response = QueryChannelResponse {
+            channel: Some(Channel {
+                state: State::Closed.into(),
+                ..Default::default()
+            }),
+            proof: vec![],
+            proof_height: None,
+        };
+        let mut deps = mock_dependencies_with_grpc(CHANNEL_PATH, response.encode_to_vec());
+
+        let channel = mock_ibc_channel("channel-0", IbcOrder::Ordered, "ics27-1");
+        let callback_address = deps.api.addr_make("callback");
+        state::STATE
+            .save(
+                &mut deps.storage,
+                &ContractState::new(Some(callback_address)),
+            )
+            .unwrap();
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                0,
+                &ChannelState::new_open_channel(channel),
+            )
+            .unwrap();
+        state::push_channel_record(
+            &mut deps.storage,
+            0,
+            &ChannelRecord {
+                channel_id: "channel-0".to_string(),
+                ica_address: "ica_address".to_string(),
+                ordering: IbcOrder::Ordered,
+                version: "ics27-1".to_string(),
+                open_height: 1,
+                close_height: None,
+                close_reason: None,
+            },
+        )
+        .unwrap();
+
+        // Anyone can sync the channel
+        let info = message_info(&deps.api.addr_make("anyone"), &[]);
+        let msg = ExecuteMsg::SyncChannel { ica_id: 0 };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "sync_channel");
+        assert_eq!(res.messages.len(), 1);
+
+        let channel_state = state::CHANNEL_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!(channel_state.channel_status, ChannelStatus::Closed);
+        let record = state::CHANNEL_HISTORY.load(&deps.storage, (0, 0)).unwrap();
+        assert_eq!(record.close_reason, Some(ChannelCloseReason::Synced));
+
+        // Nothing changes once the status is in sync
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(res.events.is_empty());
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn test_sync_channel_after_upgrade() {
+        use cosmos_sdk_proto::{
+            ibc::core::channel::v1::{Channel, Order, QueryChannelResponse, State},
+            traits::Message,
+        };
+        use cosmwasm_std::{testing::mock_ibc_channel, IbcOrder};
+
+        use crate::ibc::types::{
+            metadata::{IcaMetadata, TxEncoding},
+            stargate::channel::CHANNEL_PATH,
+        };
+
+        let version = serde_json_wasm::to_string(&IcaMetadata::new(
+            "ics27-1".to_string(),
+            "connection-0".to_string(),
+            "connection-1".to_string(),
+            "ica_address".to_string(),
+            TxEncoding::Proto3Json,
+            "sdk_multi_msg".to_string(),
+        ))
+        .unwrap();
+        let response = QueryChannelResponse {
+            channel: Some(Channel {
+                state: State::Open.into(),
+                ordering: Order::Unordered.into(),
+                version: version.clone(),
+                ..Default::default()
+            }),
+            proof: vec![],
+            proof_height: None,
+        };
+        let mut deps = mock_dependencies_with_grpc(CHANNEL_PATH, response.encode_to_vec());
+
+        let channel = mock_ibc_channel("channel-0", IbcOrder::Ordered, "ics27-1");
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                0,
+                &ChannelState::new_open_channel(channel),
+            )
+            .unwrap();
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new("ica_address", "channel-0", TxEncoding::Protobuf),
+            )
+            .unwrap();
+
+        // The status did not change, but the ordering, version and encoding are refreshed
+        let info = message_info(&deps.api.addr_make("anyone"), &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info,
+            ExecuteMsg::SyncChannel { ica_id: 0 },
+        )
+        .unwrap();
+        assert!(res.events.is_empty());
+
+        let channel_state = state::CHANNEL_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!(channel_state.channel.order, IbcOrder::Unordered);
+        assert_eq!(channel_state.channel.version, version);
+        let ica_info = state::ICA_INFO.load(&deps.storage, 0).unwrap();
+        assert_eq!(ica_info.encoding, TxEncoding::Proto3Json);
+    }
+
+    #[test]
+    fn test_channel_close_callback_error_is_ignored() {
+        let mut deps = mock_dependencies();
+
+        let msg = Reply {
+            id: keys::reply_ids::CHANNEL_CLOSE_CALLBACK,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: cosmwasm_std::SubMsgResult::Err("unknown variant".to_string()),
+        };
+        let res = reply(deps.as_mut(), mock_env(), msg).unwrap();
+        assert_eq!(res.attributes[0].value, "unknown variant");
+    }
+
+    #[test]
+    fn test_controller_module_backend() {
+        use cosmos_sdk_proto::{
+            ibc::core::channel::v1::{Channel, QueryChannelResponse, State},
+            traits::Message,
+        };
+        use cosmwasm_std::{coins, BankMsg, CosmosMsg, MsgResponse, SubMsgResponse, SubMsgResult};
+
+        use crate::ibc::types::{
+            keys::ICA_VERSION,
+            metadata::{IcaMetadata, TxEncoding},
+            stargate::{channel::CHANNEL_PATH, controller},
+        };
+        use crate::types::{msg::options::IcaBackend, state::ChannelStatus};
+
+        let address =
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cosmos").unwrap(), &[1; 20])
+                .unwrap();
+        let metadata = IcaMetadata::new(
+            ICA_VERSION.to_string(),
+            "connection-0".to_string(),
+            "connection-1".to_string(),
+            address.clone(),
+            TxEncoding::Protobuf,
+            "sdk_multi_msg".to_string(),
+        );
+        let response = QueryChannelResponse {
+            channel: Some(Channel {
+                state: State::Open.into(),
+                version: metadata.to_string(),
+                ..Default::default()
+            }),
+            proof: vec![],
+            proof_height: None,
+        };
+        let mut deps = mock_dependencies_with_grpc(CHANNEL_PATH, response.encode_to_vec());
+        let env = mock_env();
+        let info = message_info(&deps.api.addr_make("creator"), &[]);
+
+        let msg = InstantiateMsg {
+            owner: None,
+            channel_open_init_options: ChannelOpenInitOptions {
+                connection_id: "connection-0".to_string(),
+                counterparty_connection_id: "connection-1".to_string(),
+                counterparty_port_id: None,
+                channel_ordering: None,
+                expected_host_chain_id: None,
+                expected_address_prefix: None,
+                tx_encodings: vec![],
+            },
+            send_callbacks_to: None,
+            backend: Some(IcaBackend::ControllerModule),
+            allowed_host_ports: None,
+        };
+        let res = instantiate(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let reply_id = keys::reply_ids::REGISTER_INTERCHAIN_ACCOUNT;
+        assert!(matches!(res.messages.as_slice(), [submsg] if submsg.id == reply_id));
+
+        // The reply records the channel of the ICA
+        #[allow(deprecated)]
+        let result = SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/ibc.applications.interchain_accounts.controller.v1.MsgRegisterInterchainAccountResponse".to_string(),
+                value: Binary::new(anybuf::Anybuf::new().append_string(1, "channel-7").into_vec()),
+            }],
+        });
+        let reply_msg = Reply {
+            id: reply_id,
+            payload: res.messages[0].payload.clone(),
+            gas_used: 0,
+            result,
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        let channel_state = state::CHANNEL_STATE.load(&deps.storage, 0).unwrap();
+        assert_eq!(channel_state.channel_status, ChannelStatus::Init);
+        assert_eq!(
+            channel_state.channel.endpoint.port_id,
+            format!("icacontroller-{}", env.contract.address)
+        );
+
+        // Syncing the open channel completes the handshake
+        let msg = ExecuteMsg::SyncChannel { ica_id: 0 };
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let channel_state = state::CHANNEL_STATE.load(&deps.storage, 0).unwrap();
+        assert!(channel_state.is_open());
+        let ica_info = state::ICA_INFO.load(&deps.storage, 0).unwrap();
+        assert_eq!(ica_info.ica_address, address);
+        assert_eq!(ica_info.channel_id, "channel-7");
+
+        // Packets are sent with `MsgSendTx`
+        let msg = ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "receiver".to_string(),
+                amount: coins(1, "stake"),
+            })],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds: None,
+            timeout: None,
+            retry: None,
+        };
+        let res = execute(deps.as_mut(), env, info, msg).unwrap();
+        #[allow(deprecated)]
+        let CosmosMsg::Stargate { type_url, .. } = &res.messages[0].msg
+        else {
+            panic!("expected a stargate message");
+        };
+        assert_eq!(type_url, controller::SEND_TX_TYPE_URL);
+    }
+
+    #[test]
+    fn test_enqueue_and_flush() {
+        use cosmwasm_std::{
+            coins, BankMsg, BankQuery, CosmosMsg, MsgResponse, SubMsgResponse, SubMsgResult,
+        };
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        let strategy_a = message_info(&deps.api.addr_make("strategy_a"), &[]);
+        let strategy_b = message_info(&deps.api.addr_make("strategy_b"), &[]);
+
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        state::ICA_INFO
+            .save(&mut deps.storage, 0, &ica_info)
+            .unwrap();
+
+        let bank_send = |amount: u128| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "to_address".to_string(),
+                amount: coins(amount, "uatom"),
+            })
+        };
+        let enqueue =
+            |messages: Vec<CosmosMsg>, queries, correlation_id: &str| ExecuteMsg::Enqueue {
+                ica_id: 0,
+                messages,
+                queries,
+                correlation_id: Some(correlation_id.to_string()),
+            };
+
+        // Only the owner and the enqueuers can enqueue messages
+        let msg = enqueue(vec![bank_send(1)], vec![], "a");
+        let err = execute(deps.as_mut(), env.clone(), strategy_a.clone(), msg.clone()).unwrap_err();
+        assert_eq!(err.to_string(), ContractError::Unauthorized.to_string());
+        let update = ExecuteMsg::UpdateEnqueuers {
+            enqueuers: vec![strategy_a.sender.to_string(), strategy_b.sender.to_string()],
+        };
+        execute(deps.as_mut(), env.clone(), owner, update).unwrap();
+
+        execute(deps.as_mut(), env.clone(), strategy_a.clone(), msg).unwrap();
+        let balance = QueryRequest::Bank(BankQuery::Balance {
+            address: "ica_address".to_string(),
+            denom: "uatom".to_string(),
+        });
+        let msg = enqueue(vec![bank_send(2), bank_send(3)], vec![balance], "b");
+        execute(deps.as_mut(), env.clone(), strategy_b, msg).unwrap();
+
+        // The queued packet cannot exceed the maximum size
+        let large_msg = CosmosMsg::Bank(BankMsg::Send {
+            to_address: "a".repeat(crate::types::msg::MAX_QUEUED_PACKET_SIZE),
+            amount: coins(1, "uatom"),
+        });
+        let msg = enqueue(vec![large_msg], vec![], "c");
+        let err = execute(deps.as_mut(), env.clone(), strategy_a.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::QueuedPacketTooLarge { .. }));
+        assert_eq!(query::queue(deps.as_ref(), 0).unwrap().len(), 2);
+
+        // The queue is sent in a single packet
+        let flush = ExecuteMsg::Flush {
+            ica_id: 0,
+            packet_memo: None,
+            timeout_seconds: None,
+            timeout: None,
+            retry: None,
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            strategy_a.clone(),
+            flush.clone(),
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, keys::reply_ids::SEND_PACKET);
+        assert!(query::queue(deps.as_ref(), 0).unwrap().is_empty());
+        let err = execute(deps.as_mut(), env.clone(), strategy_a, flush).unwrap_err();
+        assert_eq!(err.to_string(), ContractError::QueueEmpty(0).to_string());
+
+        // The correlation ids are recorded with the sequence of the packet
+        #[allow(deprecated)]
+        let result = SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/ibc.core.channel.v1.MsgSendPacketResponse".to_string(),
+                value: Binary::new(anybuf::Anybuf::new().append_uint64(1, 5).into_vec()),
+            }],
+        });
+        let reply_msg = Reply {
+            id: keys::reply_ids::SEND_PACKET,
+            payload: res.messages[0].payload.clone(),
+            gas_used: 0,
+            result,
+        };
+        reply(deps.as_mut(), env, reply_msg).unwrap();
+
+        let correlations = state::PENDING_CORRELATIONS
+            .load(&deps.storage, ("channel-0", 5))
+            .unwrap();
+        assert_eq!(correlations.len(), 2);
+        assert_eq!(correlations[1].correlation_id.as_deref(), Some("b"));
+        assert_eq!(correlations[1].first_message_index, 1);
+        assert_eq!(correlations[1].message_count, 2);
+        assert_eq!(correlations[1].query_count, 1);
+        assert!(state::PENDING_QUERIES.has(&deps.storage, ("channel-0", 5)));
+    }
+
+    #[test]
+    fn test_dequeue_and_clear_queue() {
+        use cosmwasm_std::{coins, BankMsg, CosmosMsg, StakingMsg};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        let strategy = message_info(&deps.api.addr_make("strategy"), &[]);
+
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        state::ENQUEUERS
+            .save(&mut deps.storage, &vec![strategy.sender.clone()])
+            .unwrap();
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        state::ICA_INFO
+            .save(&mut deps.storage, 0, &ica_info)
+            .unwrap();
+
+        let enqueue = |message: CosmosMsg, correlation_id: &str| ExecuteMsg::Enqueue {
+            ica_id: 0,
+            messages: vec![message],
+            queries: vec![],
+            correlation_id: Some(correlation_id.to_string()),
+        };
+        let send = CosmosMsg::Bank(BankMsg::Send {
+            to_address: "to_address".to_string(),
+            amount: coins(1, "uatom"),
+        });
+        let delegate = CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: "validator".to_string(),
+            amount: cosmwasm_std::coin(1, "uatom"),
+        });
+        let msg = enqueue(send.clone(), "send");
+        execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+        let msg = enqueue(delegate, "delegate");
+        execute(deps.as_mut(), env.clone(), strategy.clone(), msg).unwrap();
+        let queue = query::queue(deps.as_ref(), 0).unwrap();
+        let size = state::QUEUE_INFO.load(&deps.storage, 0).unwrap().size;
+        assert_eq!(size, queue[0].size + queue[1].size);
+
+        // The host no longer allows one of the entries, so the queue cannot be flushed
+        state::HOST_ALLOW_MESSAGES
+            .save(
+                &mut deps.storage,
+                0,
+                &vec!["/cosmos.bank.v1beta1.MsgSend".to_string()],
+            )
+            .unwrap();
+        let flush = ExecuteMsg::Flush {
+            ica_id: 0,
+            packet_memo: None,
+            timeout_seconds: None,
+            timeout: None,
+            retry: None,
+        };
+        execute(deps.as_mut(), env.clone(), owner.clone(), flush.clone()).unwrap_err();
+
+        // Only the sender of the entry can dequeue it
+        let dequeue = ExecuteMsg::Dequeue {
+            ica_id: 0,
+            correlation_id: "delegate".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), owner.clone(), dequeue.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::QueueEntryNotFound("delegate".to_string()).to_string()
+        );
+        execute(deps.as_mut(), env.clone(), strategy.clone(), dequeue).unwrap();
+        let queue_info = state::QUEUE_INFO.load(&deps.storage, 0).unwrap();
+        assert_eq!((queue_info.length, queue_info.size), (1, queue[0].size));
+        execute(deps.as_mut(), env.clone(), owner.clone(), flush).unwrap();
+
+        // Only the owner can clear the queue
+        let msg = enqueue(send, "send");
+        execute(deps.as_mut(), env.clone(), strategy.clone(), msg).unwrap();
+        let clear = ExecuteMsg::ClearQueue { ica_id: 0 };
+        execute(deps.as_mut(), env.clone(), strategy, clear.clone()).unwrap_err();
+        execute(deps.as_mut(), env, owner, clear).unwrap();
+        assert!(query::queue(deps.as_ref(), 0).unwrap().is_empty());
+        assert!(!state::QUEUE_INFO.has(&deps.storage, 0));
+    }
+
+    #[test]
+    fn test_packet_timeouts() {
+        use cosmwasm_std::{CosmosMsg, IbcMsg, IbcTimeout, IbcTimeoutBlock};
+
+        use crate::types::msg::options::PacketTimeout;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        state::ICA_INFO
+            .save(&mut deps.storage, 0, &ica_info)
+            .unwrap();
+
+        let send = |timeout_seconds, timeout| ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds,
+            timeout,
+            retry: None,
+        };
+        let sent_timeout = |deps: DepsMut, msg| {
+            let res = execute(deps, env.clone(), owner.clone(), msg)?;
+            match &res.messages[0].msg {
+                CosmosMsg::Ibc(IbcMsg::SendPacket { timeout, .. }) => Ok(timeout.clone()),
+                msg => panic!("unexpected message: {msg:?}"),
+            }
+        };
+
+        // The absolute timestamp and the block height are both used
+        let block = IbcTimeoutBlock {
+            revision: 1,
+            height: 100,
+        };
+        let timestamp = env.block.time.plus_seconds(100);
+        let timeout = PacketTimeout {
+            timestamp: Some(timestamp),
+            block: Some(block),
+        };
+        assert_eq!(
+            sent_timeout(deps.as_mut(), send(None, Some(timeout.clone()))).unwrap(),
+            IbcTimeout::with_both(block, timestamp)
+        );
+
+        // Relative and absolute timeouts cannot be combined, and the timestamp must be in the future
+        let err = sent_timeout(deps.as_mut(), send(Some(10), Some(timeout))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPacketTimeout(_)));
+        let past = PacketTimeout {
+            timestamp: Some(env.block.time),
+            block: None,
+        };
+        let err = sent_timeout(deps.as_mut(), send(None, Some(past))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPacketTimeout(_)));
+
+        // A retry policy cannot be combined with an absolute timeout
+        let msg = ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds: None,
+            timeout: Some(PacketTimeout {
+                timestamp: None,
+                block: Some(block),
+            }),
+            retry: Some(crate::types::msg::options::RetryPolicy {
+                max_attempts: 1,
+                timeout_backoff_seconds: 0,
+            }),
+        };
+        let err = sent_timeout(deps.as_mut(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPacketTimeout(_)));
+
+        // The owner sets the default timeout
+        let update = ExecuteMsg::UpdateDefaultTimeout {
+            timeout_seconds: Some(30),
+        };
+        execute(deps.as_mut(), env.clone(), owner.clone(), update).unwrap();
+        assert_eq!(query::default_timeout(deps.as_ref()).unwrap(), 30);
+        assert_eq!(
+            sent_timeout(deps.as_mut(), send(None, None)).unwrap(),
+            IbcTimeout::with_timestamp(env.block.time.plus_seconds(30))
+        );
+    }
+
+    #[test]
+    fn test_pending_packets() {
+        use cosmwasm_std::{
+            coins, testing::mock_ibc_packet_ack, BankMsg, CosmosMsg, IbcAcknowledgement,
+            IbcTimeout, MsgResponse, SubMsgResponse, SubMsgResult,
+        };
+
+        use crate::ibc::{relay::ibc_packet_ack, types::packet::acknowledgement::Data};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        state::ICA_INFO
+            .save(&mut deps.storage, 0, &ica_info)
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &0)
+            .unwrap();
+
+        // Packets without queries are also replied to
+        let msg = ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "to_address".to_string(),
+                amount: coins(100, "uatom"),
+            })],
+            queries: vec![],
+            packet_memo: Some("memo".to_string()),
+            timeout_seconds: Some(60),
+            timeout: None,
+            retry: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), owner.clone(), msg).unwrap();
+        assert_eq!(res.messages[0].id, keys::reply_ids::SEND_PACKET);
+        assert!(query::pending_packets(deps.as_ref(), None, None)
+            .unwrap()
+            .is_empty());
+
+        // The reply records the packet with its sequence
+        #[allow(deprecated)]
+        let result = SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/ibc.core.channel.v1.MsgSendPacketResponse".to_string(),
+                value: Binary::new(anybuf::Anybuf::new().append_uint64(1, 29).into_vec()),
+            }],
+        });
+        let reply_msg = Reply {
+            id: keys::reply_ids::SEND_PACKET,
+            payload: res.messages[0].payload.clone(),
+            gas_used: 0,
+            result,
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+
+        let packet = query::packet(deps.as_ref(), 0, 29, None).unwrap().unwrap();
+        assert_eq!(packet.channel_id, "channel-0");
+        assert_eq!(packet.sender, owner.sender);
+        assert_eq!(packet.type_urls, vec!["/cosmos.bank.v1beta1.MsgSend"]);
+        assert_eq!(packet.memo.as_deref(), Some("memo"));
+        assert_eq!(
+            packet.timeout,
+            IbcTimeout::with_timestamp(env.block.time.plus_seconds(60))
+        );
+        assert_eq!(packet.send_height, env.block.height);
+        let pending = query::pending_packets(deps.as_ref(), None, None).unwrap();
+        assert_eq!(pending, vec![packet]);
+        let start_after = Some(("channel-0".to_string(), 29));
+        assert!(query::pending_packets(deps.as_ref(), start_after, None)
+            .unwrap()
+            .is_empty());
+
+        // The acknowledgement clears the packet
+        let ack =
+            IbcAcknowledgement::new(to_json_binary(&Data::Result(Binary::default())).unwrap());
+        let ack_msg = mock_ibc_packet_ack("channel-0", &"data", ack).unwrap();
+        ibc_packet_ack(deps.as_mut(), env, ack_msg).unwrap();
+        assert!(query::packet(deps.as_ref(), 0, 29, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_packet_history() {
+        use cosmwasm_std::{
+            testing::{mock_ibc_packet_ack, mock_ibc_packet_timeout},
+            IbcAcknowledgement, IbcTimeout,
+        };
+
+        use crate::ibc::{
+            relay::{ibc_packet_ack, ibc_packet_timeout},
+            types::packet::acknowledgement::Data,
+        };
+        use crate::types::state::packet::{PacketStatus, PendingPacket};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &0)
+            .unwrap();
+        let channel = cosmwasm_std::testing::mock_ibc_channel(
+            "channel-0",
+            cosmwasm_std::IbcOrder::Unordered,
+            "ics27-1",
+        );
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                0,
+                &state::ChannelState::new_open_channel(channel),
+            )
+            .unwrap();
+        let pending = PendingPacket {
+            ica_id: 0,
+            channel_id: "channel-0".to_string(),
+            sequence: 29,
+            sender: owner.sender.clone(),
+            type_urls: vec![],
+            memo: None,
+            timeout: IbcTimeout::with_timestamp(env.block.time),
+            send_height: 1,
+        };
+        state::PENDING_PACKETS
+            .save(&mut deps.storage, ("channel-0", 29), &pending)
+            .unwrap();
+
+        let ack = |data: Data| {
+            let ack = IbcAcknowledgement::new(to_json_binary(&data).unwrap());
+            mock_ibc_packet_ack("channel-0", &"data", ack).unwrap()
+        };
+        ibc_packet_ack(
+            deps.as_mut(),
+            env.clone(),
+            ack(Data::Result(Binary::default())),
+        )
+        .unwrap();
+        ibc_packet_ack(deps.as_mut(), env.clone(), ack(Data::Error("error".into()))).unwrap();
+        let timeout_msg = mock_ibc_packet_timeout("channel-0", &"data").unwrap();
+        ibc_packet_timeout(deps.as_mut(), env.clone(), timeout_msg).unwrap();
+
+        let history = query::packet_history(deps.as_ref(), None, None, None).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].1.status, PacketStatus::Success);
+        assert_eq!(history[0].1.packet, Some(pending));
+        assert_eq!(history[0].1.completion_height, env.block.height);
+        assert_eq!(history[0].1.ack_hash.as_ref().unwrap().len(), 32);
+        assert_eq!(history[1].1.error.as_deref(), Some("error"));
+        assert!(history[1].1.packet.is_none());
+        assert!(history[2].1.ack_hash.is_none());
+
+        let errors =
+            query::packet_history(deps.as_ref(), None, None, Some(PacketStatus::Error)).unwrap();
+        assert_eq!(errors, vec![history[1].clone()]);
+        let page = query::packet_history(deps.as_ref(), Some(0), Some(1), None).unwrap();
+        assert_eq!(page, vec![history[1].clone()]);
+
+        // Only the owner can lower the retention, and the oldest outcomes are removed first
+        let msg = ExecuteMsg::UpdatePacketHistoryRetention { retention: Some(1) };
+        let not_owner = message_info(&deps.api.addr_make("not_owner"), &[]);
+        execute(deps.as_mut(), env.clone(), not_owner, msg.clone()).unwrap_err();
+        execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+        assert_eq!(query::packet_history_retention(deps.as_ref()).unwrap(), 1);
+
+        let timeout_msg = mock_ibc_packet_timeout("channel-0", &"data").unwrap();
+        ibc_packet_timeout(deps.as_mut(), env, timeout_msg).unwrap();
+        let history = query::packet_history(deps.as_ref(), None, None, None).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].0, 3);
+        assert_eq!(history[0].1.status, PacketStatus::Timeout);
+
+        // The pruned outcomes are removed from the status index
+        let errors =
+            query::packet_history(deps.as_ref(), None, None, Some(PacketStatus::Error)).unwrap();
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_retry() {
+        use cosmwasm_std::{
+            testing::{mock_ibc_channel, mock_ibc_packet_timeout},
+            BankMsg, CosmosMsg, IbcMsg, IbcOrder, IbcTimeout, MsgResponse, SubMsgResponse,
+            SubMsgResult, WasmMsg,
+        };
+
+        use crate::{ibc::relay::ibc_packet_timeout, types::msg::options::RetryPolicy};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        let callback = deps.api.addr_make("callback");
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(Some(callback)))
+            .unwrap();
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        state::ICA_INFO
+            .save(&mut deps.storage, 0, &ica_info)
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &0)
+            .unwrap();
+        let channel = mock_ibc_channel("channel-0", IbcOrder::Unordered, "ics27-1");
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                0,
+                &ChannelState::new_open_channel(channel),
+            )
+            .unwrap();
+
+        let msg = ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "to_address".to_string(),
+                amount: cosmwasm_std::coins(100, "uatom"),
+            })],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds: Some(60),
+            timeout: None,
+            retry: Some(RetryPolicy {
+                max_attempts: 1,
+                timeout_backoff_seconds: 30,
+            }),
+        };
+        let res = execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+        let sent = |submsg: &SubMsg| match &submsg.msg {
+            CosmosMsg::Ibc(IbcMsg::SendPacket { data, timeout, .. }) => {
+                (data.clone(), timeout.clone())
+            }
+            msg => panic!("unexpected message: {msg:?}"),
+        };
+        let (data, _) = sent(&res.messages[0]);
+
+        // Records the packet and its retry policy with the sequence of the mock packets
+        let mut send_reply = |submsg: &SubMsg| {
+            #[allow(deprecated)]
+            let result = SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+                msg_responses: vec![MsgResponse {
+                    type_url: "/ibc.core.channel.v1.MsgSendPacketResponse".to_string(),
+                    value: Binary::new(anybuf::Anybuf::new().append_uint64(1, 29).into_vec()),
+                }],
+            });
+            let reply_msg = Reply {
+                id: submsg.id,
+                payload: submsg.payload.clone(),
+                gas_used: 0,
+                result,
+            };
+            reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+            let timeout_msg = mock_ibc_packet_timeout("channel-0", &"data").unwrap();
+            ibc_packet_timeout(deps.as_mut(), env.clone(), timeout_msg).unwrap()
+        };
+
+        // The first timeout resends the same packet data with the backoff, without a callback
+        let res = send_reply(&res.messages[0]);
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, keys::reply_ids::RESEND_PACKET);
+        assert_eq!(res.messages[0].reply_on, cosmwasm_std::ReplyOn::Always);
+        assert_eq!(
+            sent(&res.messages[0]),
+            (
+                data,
+                IbcTimeout::with_timestamp(env.block.time.plus_seconds(90))
+            )
+        );
+        assert_eq!(res.events[0].ty, "retry_packet");
+
+        // The second timeout reports the retry in the timeout callback
+        let res = send_reply(&res.messages[0]);
+        assert_eq!(res.messages.len(), 1);
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) = &res.messages[0].msg else {
+            panic!("unexpected message: {:?}", res.messages[0].msg);
+        };
+        let msg = String::from_utf8(msg.to_vec()).unwrap();
+        assert!(msg.contains("on_timeout_packet_callback"));
+        assert!(msg.contains(r#""retries":1"#));
+    }
+
+    #[test]
+    fn test_failed_resend() {
+        use cosmwasm_std::{
+            testing::{mock_ibc_channel, mock_ibc_packet_timeout},
+            BankMsg, CosmosMsg, IbcOrder, MsgResponse, SubMsgResponse, SubMsgResult, WasmMsg,
+        };
+
+        use crate::{ibc::relay::ibc_packet_timeout, types::msg::options::RetryPolicy};
 
-        // Ensure the admin is saved correctly
-        let owner = cw_ownable::get_ownership(&deps.storage)
-            .unwrap()
-            .owner
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let owner = message_info(&deps.api.addr_make("creator"), &[]);
+        cw_ownable::initialize_owner(&mut deps.storage, &deps.api, Some(owner.sender.as_str()))
+            .unwrap();
+        let callback = deps.api.addr_make("callback");
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(Some(callback)))
+            .unwrap();
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new(
+                    "ica_address",
+                    "channel-0",
+                    crate::ibc::types::metadata::TxEncoding::Protobuf,
+                ),
+            )
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &0)
+            .unwrap();
+        let channel = mock_ibc_channel("channel-0", IbcOrder::Unordered, "ics27-1");
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                0,
+                &ChannelState::new_open_channel(channel),
+            )
             .unwrap();
-        assert_eq!(owner, info.sender);
 
-        // Ensure that the contract name and version are saved correctly
-        let contract_version = cw2::get_contract_version(&deps.storage).unwrap();
-        assert_eq!(contract_version.contract, keys::CONTRACT_NAME);
-        assert_eq!(contract_version.version, keys::CONTRACT_VERSION);
+        let msg = ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "to_address".to_string(),
+                amount: cosmwasm_std::coins(100, "uatom"),
+            })],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds: Some(60),
+            timeout: None,
+            retry: Some(RetryPolicy {
+                max_attempts: 2,
+                timeout_backoff_seconds: 30,
+            }),
+        };
+        let res = execute(deps.as_mut(), env.clone(), owner, msg).unwrap();
+        #[allow(deprecated)]
+        let result = SubMsgResult::Ok(SubMsgResponse {
+            events: vec![],
+            data: None,
+            msg_responses: vec![MsgResponse {
+                type_url: "/ibc.core.channel.v1.MsgSendPacketResponse".to_string(),
+                value: Binary::new(anybuf::Anybuf::new().append_uint64(1, 29).into_vec()),
+            }],
+        });
+        let reply_msg = Reply {
+            id: res.messages[0].id,
+            payload: res.messages[0].payload.clone(),
+            gas_used: 0,
+            result,
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        let timeout_msg = mock_ibc_packet_timeout("channel-0", &"data").unwrap();
+        let res = ibc_packet_timeout(deps.as_mut(), env.clone(), timeout_msg).unwrap();
+
+        // The resend fails, so the timeout callback is sent without counting the failed attempt
+        let reply_msg = Reply {
+            id: res.messages[0].id,
+            payload: res.messages[0].payload.clone(),
+            gas_used: 0,
+            result: SubMsgResult::Err("channel not found".to_string()),
+        };
+        let res = reply(deps.as_mut(), env, reply_msg).unwrap();
+        assert_eq!(res.events[0].ty, "retry_packet_failed");
+        let CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) = &res.messages[0].msg else {
+            panic!("unexpected message: {:?}", res.messages[0].msg);
+        };
+        let msg = String::from_utf8(msg.to_vec()).unwrap();
+        assert!(msg.contains("on_timeout_packet_callback"));
+        assert!(!msg.contains("retries"));
+        assert!(state::QUERY.is_empty(&deps.storage));
+        assert!(state::RESENT_PACKETS.is_empty(&deps.storage));
     }
 
     #[test]
-    fn test_update_callback_address() {
+    fn test_host_allow_messages() {
+        use cosmwasm_std::{coins, BankMsg, CosmosMsg, StakingMsg};
+
         let mut deps = mock_dependencies();
 
         let creator = deps.api.addr_make("creator");
@@ -427,6 +3242,9 @@ mod tests {
             counterparty_connection_id: "connection-1".to_string(),
             counterparty_port_id: None,
             channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
         // Instantiate the contract
@@ -438,37 +3256,402 @@ mod tests {
                 owner: None,
                 channel_open_init_options,
                 send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
             },
         )
         .unwrap();
 
-        // Ensure the contract admin can update the callback address
-        let new_callback_address = deps.api.addr_make("new_callback_address").to_string();
-        let msg = ExecuteMsg::UpdateCallbackAddress {
-            callback_address: Some(new_callback_address.clone()),
+        // Set the ica info as if the handshake was completed
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new(
+                    "ica_address",
+                    "channel-0",
+                    crate::ibc::types::metadata::TxEncoding::Protobuf,
+                ),
+            )
+            .unwrap();
+
+        let send_cosmos_msgs = |msg: CosmosMsg| ExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
+            messages: vec![msg],
+            queries: vec![],
+            packet_memo: None,
+            timeout_seconds: None,
+            timeout: None,
+            retry: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let send_msg = send_cosmos_msgs(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "to_address".to_string(),
+            amount: coins(100, "uatom"),
+        }));
+        let delegate_msg = send_cosmos_msgs(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: "validator".to_string(),
+            amount: cosmwasm_std::coin(100, "uatom"),
+        }));
+
+        // Messages are not validated if the host's allow list is not cached
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            delegate_msg.clone(),
+        )
+        .unwrap();
 
-        assert_eq!(0, res.messages.len());
+        state::HOST_ALLOW_MESSAGES
+            .save(
+                &mut deps.storage,
+                0,
+                &vec!["/cosmos.bank.v1beta1.MsgSend".to_string()],
+            )
+            .unwrap();
 
-        let state = state::STATE.load(&deps.storage).unwrap();
+        execute(deps.as_mut(), env.clone(), info.clone(), send_msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            delegate_msg.clone(),
+        )
+        .unwrap_err();
         assert_eq!(
-            state.callback_address,
-            Some(deps.api.addr_validate(&new_callback_address).unwrap())
+            err.to_string(),
+            ContractError::MessageNotAllowedByHost(
+                "/cosmos.staking.v1beta1.MsgDelegate".to_string()
+            )
+            .to_string()
         );
 
-        // Ensure a non-admin cannot update the callback address
-        let non_admin = deps.api.addr_make("non-admin");
-        let info = message_info(&non_admin, &[]);
-        let msg = ExecuteMsg::UpdateCallbackAddress {
-            callback_address: Some("new_callback_address".to_string()),
+        // The refresh is not blocked by the cached allow list
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::RefreshHostAllowMessages { ica_id: 0 },
+        )
+        .unwrap();
+        assert_eq!(res.messages[0].id, keys::reply_ids::SEND_PACKET);
+
+        // The wildcard allows all messages
+        state::HOST_ALLOW_MESSAGES
+            .save(&mut deps.storage, 0, &vec!["*".to_string()])
+            .unwrap();
+        execute(deps.as_mut(), env, info, delegate_msg).unwrap();
+    }
+
+    #[test]
+    fn test_query_subscriptions() {
+        use cosmwasm_std::{coin, BankMsg, BankQuery, CosmosMsg, QueryRequest};
+
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let mut env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
-        let res = execute(deps.as_mut(), env, info, msg);
+        // Instantiate the contract
+        let _res = instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options,
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+
+        // Set the ica info as if the handshake was completed
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new(
+                    "ica_address",
+                    "channel-0",
+                    crate::ibc::types::metadata::TxEncoding::Protobuf,
+                ),
+            )
+            .unwrap();
+
+        let register_msg = ExecuteMsg::RegisterQuerySubscription {
+            ica_id: 0,
+            queries: vec![QueryRequest::Bank(BankQuery::Balance {
+                address: "ica_address".to_string(),
+                denom: "uatom".to_string(),
+            })],
+            interval_seconds: 60,
+            cache_results: true,
+            bounty: Some(coin(100, "stake")),
+        };
+
+        let bot = deps.api.addr_make("bot");
+        let bot_info = message_info(&bot, &[]);
+        let crank = ExecuteMsg::Crank { limit: None };
+        // Only the owner can register subscriptions
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            bot_info.clone(),
+            register_msg.clone(),
+        )
+        .unwrap_err();
+        execute(deps.as_mut(), env.clone(), info, register_msg).unwrap();
+        deps.querier
+            .bank
+            .update_balance(&env.contract.address, vec![coin(100, "stake")]);
+
+        // Anyone can crank a due subscription and receives the bounty
+        let res = execute(deps.as_mut(), env.clone(), bot_info.clone(), crank.clone()).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(res.messages[0].id, keys::reply_ids::SEND_PACKET);
         assert_eq!(
-            res.unwrap_err().to_string(),
-            "Caller is not the contract's current owner".to_string()
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: bot.to_string(),
+                amount: vec![coin(100, "stake")],
+            })
+        );
+        assert_eq!(
+            state::QUERY.load(&deps.storage, 0).unwrap().subscription_id,
+            Some(0)
+        );
+
+        // The subscription is not due until the interval has passed
+        let err = execute(deps.as_mut(), env.clone(), bot_info.clone(), crank.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::NoDueQuerySubscriptions.to_string()
+        );
+
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(deps.as_mut(), env.clone(), bot_info.clone(), crank.clone()).unwrap();
+
+        let subscription = query::query_subscription(deps.as_ref(), 0).unwrap();
+        assert_eq!(subscription.next_run, env.block.time.plus_seconds(60));
+
+        // Cancelled subscriptions cannot be cranked
+        let info = message_info(&creator, &[]);
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            info,
+            ExecuteMsg::CancelQuerySubscription { subscription_id: 0 },
+        )
+        .unwrap();
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(deps.as_mut(), env, bot_info, crank).unwrap_err();
+    }
+
+    #[test]
+    fn test_crank_skips_rejected_subscriptions() {
+        use cosmwasm_std::{BankQuery, QueryRequest};
+
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options,
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+
+        for ica_id in 0..2 {
+            state::ICA_INFO
+                .save(
+                    &mut deps.storage,
+                    ica_id,
+                    &state::IcaInfo::new(
+                        "ica_address",
+                        format!("channel-{ica_id}"),
+                        crate::ibc::types::metadata::TxEncoding::Protobuf,
+                    ),
+                )
+                .unwrap();
+            let register_msg = ExecuteMsg::RegisterQuerySubscription {
+                ica_id,
+                queries: vec![QueryRequest::Bank(BankQuery::Balance {
+                    address: "ica_address".to_string(),
+                    denom: "uatom".to_string(),
+                })],
+                interval_seconds: 60,
+                cache_results: false,
+                bounty: None,
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), register_msg).unwrap();
+        }
+
+        // The host of the first ICA does not allow queries
+        state::HOST_ALLOW_MESSAGES
+            .save(
+                &mut deps.storage,
+                0,
+                &vec!["/cosmos.bank.v1beta1.MsgSend".to_string()],
+            )
+            .unwrap();
+
+        let bot_info = message_info(&deps.api.addr_make("bot"), &[]);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            bot_info,
+            ExecuteMsg::Crank { limit: None },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            state::QUERY.load(&deps.storage, 0).unwrap().subscription_id,
+            Some(1)
+        );
+        assert_eq!(1, res.events.len());
+        assert_eq!(res.events[0].ty, "skip_query_subscription");
+        assert_eq!(res.events[0].attributes[0].value, "0");
+
+        // The skipped subscription is rescheduled with the others
+        let subscription = query::query_subscription(deps.as_ref(), 0).unwrap();
+        assert_eq!(subscription.next_run, env.block.time.plus_seconds(60));
+    }
+
+    #[test]
+    fn test_crank_skips_unpaid_bounties() {
+        use cosmwasm_std::{coin, BankMsg, BankQuery, CosmosMsg, IbcQuery, QueryRequest};
+
+        let mut deps = mock_dependencies();
+
+        let creator = deps.api.addr_make("creator");
+        let info = message_info(&creator, &[]);
+        let env = mock_env();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        instantiate(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            InstantiateMsg {
+                owner: None,
+                channel_open_init_options,
+                send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
+            },
+        )
+        .unwrap();
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new(
+                    "ica_address",
+                    "channel-0",
+                    crate::ibc::types::metadata::TxEncoding::Protobuf,
+                ),
+            )
+            .unwrap();
+
+        // Unsupported queries are rejected on registration
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            ExecuteMsg::RegisterQuerySubscription {
+                ica_id: 0,
+                queries: vec![QueryRequest::Ibc(IbcQuery::PortId {})],
+                interval_seconds: 60,
+                cache_results: false,
+                bounty: None,
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::UnsupportedQuery(_)));
+
+        for _ in 0..2 {
+            let register_msg = ExecuteMsg::RegisterQuerySubscription {
+                ica_id: 0,
+                queries: vec![QueryRequest::Bank(BankQuery::Balance {
+                    address: "ica_address".to_string(),
+                    denom: "uatom".to_string(),
+                })],
+                interval_seconds: 60,
+                cache_results: false,
+                bounty: Some(coin(100, "stake")),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), register_msg).unwrap();
+        }
+
+        // The contract can only pay the bounty of the first subscription
+        deps.querier
+            .bank
+            .update_balance(&env.contract.address, vec![coin(150, "stake")]);
+
+        let bot = deps.api.addr_make("bot");
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&bot, &[]),
+            ExecuteMsg::Crank { limit: None },
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[1].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: bot.to_string(),
+                amount: vec![coin(100, "stake")],
+            })
         );
+        assert_eq!(1, res.events.len());
+        assert_eq!(res.events[0].ty, "skip_query_subscription");
+        assert_eq!(res.events[0].attributes[0].value, "1");
+
+        let subscription = query::query_subscription(deps.as_ref(), 1).unwrap();
+        assert_eq!(subscription.next_run, env.block.time.plus_seconds(60));
     }
 
     // In this test, we aim to verify that the semver validation is performed correctly.
@@ -485,6 +3668,9 @@ mod tests {
             counterparty_connection_id: "connection-1".to_string(),
             counterparty_port_id: None,
             channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
         // Instantiate the contract
@@ -496,6 +3682,8 @@ mod tests {
                 owner: None,
                 channel_open_init_options,
                 send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
             },
         )
         .unwrap();
@@ -540,6 +3728,9 @@ mod tests {
             counterparty_connection_id: "connection-1".to_string(),
             counterparty_port_id: None,
             channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
         // Instantiate the contract
@@ -551,6 +3742,8 @@ mod tests {
                 owner: None,
                 channel_open_init_options,
                 send_callbacks_to: None,
+                backend: None,
+                allowed_host_ports: None,
             },
         )
         .unwrap();
@@ -563,37 +3756,114 @@ mod tests {
         assert_eq!(contract_version.contract, keys::CONTRACT_NAME);
         assert_eq!(contract_version.version, "0.0.1");
 
-        // Set the encoding to proto3json
-        state::STATE
-            .update::<_, StdError>(&mut deps.storage, |mut state| {
-                state.set_ica_info("", "", crate::ibc::types::metadata::TxEncoding::Proto3Json);
-                Ok(state)
-            })
+        // The encodings negotiated with `tx_encodings` are kept by the migration
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &state::IcaInfo::new("", "", crate::ibc::types::metadata::TxEncoding::Proto3Json),
+            )
             .unwrap();
 
-        // Migration should fail because the encoding is not protobuf
-        let err = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap_err();
+        let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        let contract_version = cw2::get_contract_version(&deps.storage).unwrap();
+        assert_eq!(contract_version.contract, keys::CONTRACT_NAME);
+        assert_eq!(contract_version.version, keys::CONTRACT_VERSION);
         assert_eq!(
-            err.to_string(),
-            ContractError::UnsupportedPacketEncoding(
-                crate::ibc::types::metadata::TxEncoding::Proto3Json.to_string()
-            )
-            .to_string()
+            state::ICA_INFO.load(&deps.storage, 0).unwrap().encoding,
+            crate::ibc::types::metadata::TxEncoding::Proto3Json
         );
+    }
 
-        // Set the encoding to protobuf
-        state::STATE
-            .update::<_, StdError>(&mut deps.storage, |mut state| {
-                state.set_ica_info("", "", crate::ibc::types::metadata::TxEncoding::Protobuf);
-                Ok(state)
-            })
+    #[test]
+    fn test_migrate_single_ica_state() {
+        use cosmwasm_std::{IbcChannel, IbcEndpoint, IbcOrder};
+
+        let mut deps = mock_dependencies();
+
+        let channel_open_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+        let ica_info = state::IcaInfo::new(
+            "ica_address",
+            "channel-0",
+            crate::ibc::types::metadata::TxEncoding::Protobuf,
+        );
+        let channel = IbcChannel::new(
+            IbcEndpoint {
+                port_id: "wasm.contract".to_string(),
+                channel_id: "channel-0".to_string(),
+            },
+            IbcEndpoint {
+                port_id: crate::ibc::types::keys::HOST_PORT_ID.to_string(),
+                channel_id: "channel-1".to_string(),
+            },
+            IbcOrder::Ordered,
+            "ics27-1",
+            "connection-0",
+        );
+
+        // Save the state of a contract that predates multiple ICAs
+        cw2::set_contract_version(&mut deps.storage, keys::CONTRACT_NAME, "0.0.1").unwrap();
+        state::legacy::STATE
+            .save(
+                &mut deps.storage,
+                &state::legacy::State {
+                    ica_info: Some(ica_info.clone()),
+                    callback_address: None,
+                },
+            )
+            .unwrap();
+        state::legacy::CHANNEL_OPEN_INIT_OPTIONS
+            .save(&mut deps.storage, &channel_open_init_options)
+            .unwrap();
+        state::legacy::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                &ChannelState::new_open_channel(channel.clone()),
+            )
             .unwrap();
 
-        // Migration should succeed because the encoding is protobuf
         let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
 
-        let contract_version = cw2::get_contract_version(&deps.storage).unwrap();
-        assert_eq!(contract_version.contract, keys::CONTRACT_NAME);
-        assert_eq!(contract_version.version, keys::CONTRACT_VERSION);
+        // The state is moved to ica 0
+        assert_eq!(state::ICA_INFO.load(&deps.storage, 0).unwrap(), ica_info);
+        assert_eq!(
+            state::CHANNEL_STATE.load(&deps.storage, 0).unwrap().channel,
+            channel
+        );
+        assert_eq!(
+            state::CHANNEL_OPEN_INIT_OPTIONS
+                .load(&deps.storage, 0)
+                .unwrap(),
+            channel_open_init_options
+        );
+        assert_eq!(
+            state::load_channel_ica_id(&deps.storage, "channel-0").unwrap(),
+            0
+        );
+        // The older channels of the single ICA are not in the map
+        assert_eq!(
+            state::load_channel_ica_id(&deps.storage, "channel-7").unwrap(),
+            0
+        );
+        assert_eq!(
+            state::CONNECTION_ICA_ID
+                .load(&deps.storage, "connection-0")
+                .unwrap(),
+            0
+        );
+        assert!(state::legacy::CHANNEL_STATE
+            .may_load(&deps.storage)
+            .unwrap()
+            .is_none());
+        assert!(state::STATE.load(&deps.storage).is_ok());
     }
 }
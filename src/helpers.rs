@@ -198,9 +198,19 @@ impl<'a> CwIcaControllerContractQuerier<'a> {
     /// # Errors
     ///
     /// This function returns an error if the query fails
-    pub fn get_channel(&self) -> StdResult<state::ChannelState> {
+    pub fn get_channel(&self, ica_id: u64) -> StdResult<state::ChannelState> {
         self.querier
-            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetChannel {})
+            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetChannel { ica_id })
+    }
+
+    /// `get_ica_info` sends a [`msg::QueryMsg::GetIcaInfo`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    pub fn get_ica_info(&self, ica_id: u64) -> StdResult<state::IcaInfo> {
+        self.querier
+            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetIcaInfo { ica_id })
     }
 
     /// `get_contract_state` sends a [`msg::QueryMsg::GetContractState`] query to this contract.
@@ -213,6 +223,92 @@ impl<'a> CwIcaControllerContractQuerier<'a> {
             .query_wasm_smart(&self.addr, &msg::QueryMsg::GetContractState {})
     }
 
+    /// `get_allowed_host_ports` sends a [`msg::QueryMsg::GetAllowedHostPorts`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    pub fn get_allowed_host_ports(&self) -> StdResult<Vec<String>> {
+        self.querier
+            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetAllowedHostPorts {})
+    }
+
+    /// `get_auto_reopen_policy` sends a [`msg::QueryMsg::GetAutoReopenPolicy`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    pub fn get_auto_reopen_policy(
+        &self,
+        ica_id: u64,
+    ) -> StdResult<Option<msg::options::AutoReopenPolicy>> {
+        self.querier
+            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetAutoReopenPolicy { ica_id })
+    }
+
+    /// `get_host_allow_messages` sends a [`msg::QueryMsg::GetHostAllowMessages`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    pub fn get_host_allow_messages(&self, ica_id: u64) -> StdResult<Option<Vec<String>>> {
+        self.querier
+            .query_wasm_smart(&self.addr, &msg::QueryMsg::GetHostAllowMessages { ica_id })
+    }
+
+    /// `get_channel_history` sends a [`msg::QueryMsg::GetChannelHistory`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    pub fn get_channel_history(
+        &self,
+        ica_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<Vec<(u64, state::ChannelRecord)>> {
+        self.querier.query_wasm_smart(
+            &self.addr,
+            &msg::QueryMsg::GetChannelHistory {
+                ica_id,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    /// `get_query_subscription` sends a [`msg::QueryMsg::GetQuerySubscription`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    #[cfg(feature = "query")]
+    pub fn get_query_subscription(
+        &self,
+        subscription_id: u64,
+    ) -> StdResult<state::ica_query::Subscription> {
+        self.querier.query_wasm_smart(
+            &self.addr,
+            &msg::QueryMsg::GetQuerySubscription { subscription_id },
+        )
+    }
+
+    /// `get_query_subscription_result` sends a [`msg::QueryMsg::GetQuerySubscriptionResult`] query to this contract.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the query fails
+    #[cfg(feature = "query")]
+    pub fn get_query_subscription_result(
+        &self,
+        subscription_id: u64,
+    ) -> StdResult<Option<state::ica_query::SubscriptionResult>> {
+        self.querier.query_wasm_smart(
+            &self.addr,
+            &msg::QueryMsg::GetQuerySubscriptionResult { subscription_id },
+        )
+    }
+
     /// `ownership` sends a [`msg::QueryMsg::Ownership`] query to this contract.
     ///
     /// # Errors
@@ -2,13 +2,21 @@
 
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    DepsMut, Env, Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel, IbcChannelCloseMsg,
-    IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse,
+    to_json_binary, CosmosMsg, DepsMut, Env, Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel,
+    IbcChannelCloseMsg, IbcChannelConnectMsg, IbcChannelOpenMsg, IbcChannelOpenResponse, StdResult,
+    Storage, SubMsg,
 };
 
-use super::types::{keys, metadata::IcaMetadata};
+use super::types::{
+    keys,
+    metadata::IcaMetadata,
+    stargate::{channel::new_ica_channel_open_init_cosmos_msg, controller},
+};
 use crate::types::{
-    state::{self, ChannelState},
+    callbacks::IcaControllerCallbackMsg,
+    keys as contract_keys,
+    msg::options::{ChannelOpenInitOptions, IcaBackend},
+    state::{self, ChannelCloseReason, ChannelState},
     ContractError,
 };
 
@@ -19,9 +27,11 @@ use crate::types::{
 ///
 /// This function returns an error if:
 ///
+/// - The message is `OpenTry`.
 /// - The channel is already open.
 /// - `allow_channel_open_init` is disabled.
-/// - The host port is invalid.
+/// - The connection is not reserved for any ICA of this contract.
+/// - The host port is not allowed.
 /// - Version metadata is invalid.
 #[entry_point]
 #[allow(clippy::needless_pass_by_value)] // entry point needs this signature
@@ -32,7 +42,12 @@ pub fn ibc_channel_open(
 ) -> Result<IbcChannelOpenResponse, ContractError> {
     match msg {
         IbcChannelOpenMsg::OpenInit { channel } => ibc_channel_open::init(deps, channel),
-        IbcChannelOpenMsg::OpenTry { .. } => unreachable!(),
+        IbcChannelOpenMsg::OpenTry { channel, .. } => {
+            Err(ContractError::ChannelOpenTryNotAllowed {
+                counterparty_port_id: channel.counterparty_endpoint.port_id,
+                connection_id: channel.connection_id,
+            })
+        }
     }
 }
 
@@ -43,6 +58,7 @@ pub fn ibc_channel_open(
 ///
 /// This function returns an error if:
 ///
+/// - The message is `OpenConfirm`.
 /// - The host port is invalid.
 /// - The version metadata is invalid.
 /// - The ICA address is empty.
@@ -50,15 +66,19 @@ pub fn ibc_channel_open(
 #[allow(clippy::needless_pass_by_value)] // entry point needs this signature
 pub fn ibc_channel_connect(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcChannelConnectMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     match msg {
         IbcChannelConnectMsg::OpenAck {
             channel,
             counterparty_version,
-        } => ibc_channel_open::on_acknowledgement(deps, channel, counterparty_version),
-        IbcChannelConnectMsg::OpenConfirm { .. } => unreachable!(),
+        } => ibc_channel_open::on_acknowledgement(deps, env, channel, counterparty_version),
+        IbcChannelConnectMsg::OpenConfirm { channel } => {
+            Err(ContractError::ChannelOpenConfirmNotAllowed {
+                channel_id: channel.endpoint.channel_id,
+            })
+        }
     }
 }
 
@@ -75,23 +95,122 @@ pub fn ibc_channel_connect(
 #[allow(clippy::needless_pass_by_value)] // entry point needs this signature
 pub fn ibc_channel_close(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcChannelCloseMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
     match msg {
-        IbcChannelCloseMsg::CloseInit { channel } => ibc_channel_close::init(deps, channel),
-        IbcChannelCloseMsg::CloseConfirm { channel } => ibc_channel_close::confirm(deps, channel),
+        IbcChannelCloseMsg::CloseInit { channel } => ibc_channel_close::init(deps, env, channel),
+        IbcChannelCloseMsg::CloseConfirm { channel } => {
+            ibc_channel_close::confirm(deps, env, channel)
+        }
     }
 }
 
-mod ibc_channel_open {
-    use crate::types::callbacks::IcaControllerCallbackMsg;
+/// Returns the [`IcaControllerCallbackMsg::OnChannelCloseCallback`] submessage for a closed
+/// channel, if a callback address is set.
+///
+/// The submessage is only replied to on error, and the reply ignores the error, so that a
+/// callback receiver that fails, or cannot deserialize this callback, does not revert the close.
+///
+/// # Errors
+///
+/// Returns an error if the contract state cannot be loaded or the message cannot be serialized.
+pub(crate) fn channel_close_callback(
+    storage: &dyn Storage,
+    ica_id: u64,
+    channel: IbcChannel,
+    reason: ChannelCloseReason,
+) -> StdResult<Option<SubMsg>> {
+    state::STATE
+        .load(storage)?
+        .callback_address
+        .map(|callback_address| {
+            IcaControllerCallbackMsg::OnChannelCloseCallback {
+                channel,
+                reason,
+                ica_id,
+            }
+            .into_cosmos_msg(callback_address)
+            .map(|msg| {
+                SubMsg::reply_on_error(msg, contract_keys::reply_ids::CHANNEL_CLOSE_CALLBACK)
+            })
+        })
+        .transpose()
+}
+
+/// Returns the message that opens a channel for the ICA with the given options, according to
+/// the backend of the contract.
+///
+/// With the [`IcaBackend::WasmPort`] backend, this is a `MsgChannelOpenInit` on the contract's
+/// port. With the [`IcaBackend::ControllerModule`] backend, this is a `MsgRegisterInterchainAccount`
+/// whose reply records the channel id for the ICA.
+///
+/// # Errors
+///
+/// Returns an error if the contract state cannot be loaded or saved.
+pub(crate) fn channel_open_submsg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    ica_id: u64,
+    options: ChannelOpenInitOptions,
+) -> StdResult<SubMsg> {
+    match state::STATE.load(storage)?.backend {
+        IcaBackend::WasmPort => {
+            state::ALLOW_CHANNEL_OPEN_INIT.save(storage, &true)?;
+
+            let tx_encoding = options.proposed_tx_encoding();
+            Ok(SubMsg::new(new_ica_channel_open_init_cosmos_msg(
+                env.contract.address.to_string(),
+                options.connection_id,
+                options.counterparty_port_id,
+                options.counterparty_connection_id,
+                Some(tx_encoding),
+                options.channel_ordering,
+            )))
+        }
+        IcaBackend::ControllerModule => Ok(SubMsg::reply_on_success(
+            controller::new_register_interchain_account_msg(
+                env.contract.address.as_str(),
+                &options,
+            ),
+            contract_keys::reply_ids::REGISTER_INTERCHAIN_ACCOUNT,
+        )
+        .with_payload(to_json_binary(&ica_id)?)),
+    }
+}
+
+pub(crate) mod ibc_channel_open {
+    use crate::{
+        ibc::{
+            relay::scheduled_retry_submsgs,
+            types::metadata::{validate_ica_address, TxEncoding},
+        },
+        types::state::{ChannelRecord, IcaInfo},
+    };
 
     use super::{
-        keys, state, ChannelState, ContractError, DepsMut, Ibc3ChannelOpenResponse,
-        IbcBasicResponse, IbcChannel, IbcChannelOpenResponse, IcaMetadata,
+        keys, state, ChannelOpenInitOptions, ChannelState, ContractError, CosmosMsg, DepsMut, Env,
+        Ibc3ChannelOpenResponse, IbcBasicResponse, IbcChannel, IbcChannelOpenResponse,
+        IcaControllerCallbackMsg, IcaMetadata,
     };
 
+    /// Returns an error if the tx encoding is not accepted by the channel open init options.
+    /// Only [`TxEncoding::Protobuf`] is accepted if the options are not stored.
+    fn validate_tx_encoding(
+        options: Option<&ChannelOpenInitOptions>,
+        encoding: &TxEncoding,
+    ) -> Result<(), ContractError> {
+        let accepted = options.map_or(encoding == &TxEncoding::Protobuf, |options| {
+            options.accepts_tx_encoding(encoding)
+        });
+        if !accepted {
+            return Err(ContractError::UnsupportedPacketEncoding(
+                encoding.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Handles the `OpenInit` part of the IBC handshake.
     #[allow(clippy::needless_pass_by_value)]
     pub fn init(
@@ -107,15 +226,20 @@ mod ibc_channel_open {
 
         state::ALLOW_CHANNEL_OPEN_INIT.save(deps.storage, &false)?;
 
+        // The connection determines which ICA this channel is opened for
+        let ica_id = state::CONNECTION_ICA_ID
+            .may_load(deps.storage, &channel.connection_id)?
+            .ok_or(ContractError::InvalidConnection)?;
+
         // Validate the host port
-        if channel.counterparty_endpoint.port_id != keys::HOST_PORT_ID {
+        if !state::is_host_port_allowed(deps.storage, &channel.counterparty_endpoint.port_id)? {
             return Err(ContractError::InvalidHostPort);
         }
 
         // serde::Deserialize the metadata
         let metadata: IcaMetadata = if channel.version.is_empty() {
             // if empty, use create new metadata.
-            IcaMetadata::from_channel(deps.as_ref(), &channel)?
+            IcaMetadata::from_channel(deps.as_ref(), ica_id, &channel)?
         } else {
             serde_json_wasm::from_str(&channel.version).map_err(|_| {
                 ContractError::UnknownDataType(
@@ -124,16 +248,19 @@ mod ibc_channel_open {
             })?
         };
         metadata.validate(&channel)?;
+        let options = state::CHANNEL_OPEN_INIT_OPTIONS.may_load(deps.storage, ica_id)?;
+        validate_tx_encoding(options.as_ref(), &metadata.encoding)?;
 
         // Check if the channel is already exists
-        if let Some(channel_state) = state::CHANNEL_STATE.may_load(deps.storage)? {
-            // this contract can only store one active channel
+        if let Some(channel_state) = state::CHANNEL_STATE.may_load(deps.storage, ica_id)? {
+            // each ica can only have one active channel
             // if the channel is already open, return an error
             if channel_state.is_open() {
                 return Err(ContractError::ActiveChannelAlreadySet);
             }
         }
         // Channel state need not be saved here, as it is tracked by wasmd during the handshake
+        state::CHANNEL_ICA_ID.save(deps.storage, &channel.endpoint.channel_id, &ica_id)?;
 
         Ok(IbcChannelOpenResponse::Some(Ibc3ChannelOpenResponse {
             version: metadata.to_string(),
@@ -143,11 +270,12 @@ mod ibc_channel_open {
     /// Handles the `OpenAck` part of the IBC handshake.
     #[allow(clippy::needless_pass_by_value)]
     pub fn on_acknowledgement(
-        deps: DepsMut,
-        mut channel: IbcChannel,
+        mut deps: DepsMut,
+        env: Env,
+        channel: IbcChannel,
         counterparty_version: String,
     ) -> Result<IbcBasicResponse, ContractError> {
-        let mut state = state::STATE.load(deps.storage)?;
+        let ica_id = state::load_channel_ica_id(deps.storage, &channel.endpoint.channel_id)?;
 
         // portID cannot be host chain portID
         // this is not possible since it is wasm.CONTRACT_ADDRESS
@@ -156,8 +284,35 @@ mod ibc_channel_open {
             return Err(ContractError::InvalidControllerPort);
         }
 
+        let callback_msg = complete(deps.branch(), &env, ica_id, channel, counterparty_version)?;
+
+        // The packets that timed out on the previous ordered channel are resent on this channel
+        let retries = scheduled_retry_submsgs(deps.storage, &env, ica_id)?;
+
+        Ok(IbcBasicResponse::default()
+            .add_messages(callback_msg)
+            .add_submessages(retries))
+    }
+
+    /// Validates the counterparty version of an ICA channel whose handshake completed, and
+    /// saves the channel as open. Returns the [`IcaControllerCallbackMsg::OnChannelOpenAckCallback`]
+    /// message if a callback address is set.
+    ///
+    /// This is shared by the `OpenAck` entry point and by the
+    /// [`IcaBackend::ControllerModule`](crate::types::msg::options::IcaBackend) backend, which
+    /// learns about the completed handshake by querying the channel.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn complete(
+        deps: DepsMut,
+        env: &Env,
+        ica_id: u64,
+        mut channel: IbcChannel,
+        counterparty_version: String,
+    ) -> Result<Option<CosmosMsg>, ContractError> {
+        let state = state::STATE.load(deps.storage)?;
+
         // serde::Deserialize the metadata
-        let metadata: IcaMetadata =
+        let mut metadata: IcaMetadata =
             serde_json_wasm::from_str(&counterparty_version).map_err(|_| {
                 ContractError::UnknownDataType(
                     "cannot unmarshal ICS-27 interchain accounts metadata".to_string(),
@@ -170,55 +325,118 @@ mod ibc_channel_open {
             return Err(ContractError::InvalidIcaAddress);
         }
 
-        // update state with the ica info
-        state.set_ica_info(
+        // The host may counter-propose any of the encodings accepted in the options
+        let options = state::CHANNEL_OPEN_INIT_OPTIONS.may_load(deps.storage, ica_id)?;
+        validate_tx_encoding(options.as_ref(), &metadata.encoding)?;
+
+        // Check the prefix of the address, and store it in its canonical form
+        metadata.address = validate_ica_address(
             &metadata.address,
-            &channel.endpoint.channel_id,
-            metadata.encoding.clone(),
-        );
-        state::STATE.save(deps.storage, &state)?;
+            options
+                .as_ref()
+                .and_then(|options| options.expected_address_prefix.as_deref()),
+        )?;
+
+        // A reopened channel must map to the same interchain account,
+        // unless the owner allowed a new one when creating the channel
+        if let Some(ica_info) = state::ICA_INFO.may_load(deps.storage, ica_id)? {
+            if ica_info.ica_address != metadata.address
+                && !state::ALLOW_NEW_ICA_ADDRESS
+                    .may_load(deps.storage, ica_id)?
+                    .unwrap_or_default()
+            {
+                return Err(ContractError::IcaAddressChanged {
+                    expected: ica_info.ica_address,
+                    actual: metadata.address,
+                });
+            }
+        }
+        state::ALLOW_NEW_ICA_ADDRESS.remove(deps.storage, ica_id);
+
+        // update state with the ica info, including the host chain id verified on channel creation
+        let host_chain_id = options.and_then(|options| options.expected_host_chain_id);
+        state::ICA_INFO.save(
+            deps.storage,
+            ica_id,
+            &IcaInfo {
+                host_chain_id,
+                ..IcaInfo::new(
+                    &metadata.address,
+                    &channel.endpoint.channel_id,
+                    metadata.encoding.clone(),
+                )
+            },
+        )?;
 
         channel.version = counterparty_version;
 
+        // The handshake completed, so the automatic reopen attempts start over
+        state::AUTO_REOPEN_ATTEMPTS.remove(deps.storage, ica_id);
+
+        // Append the channel to the history of the ICA
+        state::push_channel_record(
+            deps.storage,
+            ica_id,
+            &ChannelRecord {
+                channel_id: channel.endpoint.channel_id.clone(),
+                ica_address: metadata.address.clone(),
+                ordering: channel.order.clone(),
+                version: channel.version.clone(),
+                open_height: env.block.height,
+                close_height: None,
+                close_reason: None,
+            },
+        )?;
+
         // Save the channel state
         state::CHANNEL_STATE.save(
             deps.storage,
+            ica_id,
             &ChannelState::new_open_channel(channel.clone()),
         )?;
 
         // make callback if needed
-        if let Some(callback_address) = state.callback_address {
-            let callback_msg = IcaControllerCallbackMsg::OnChannelOpenAckCallback {
-                channel,
-                ica_address: metadata.address,
-                tx_encoding: metadata.encoding,
-            }
-            .into_cosmos_msg(callback_address)?;
-
-            Ok(IbcBasicResponse::default().add_message(callback_msg))
-        } else {
-            Ok(IbcBasicResponse::default())
-        }
+        state
+            .callback_address
+            .map(|callback_address| {
+                IcaControllerCallbackMsg::OnChannelOpenAckCallback {
+                    channel,
+                    ica_address: metadata.address,
+                    tx_encoding: metadata.encoding,
+                    ica_id,
+                }
+                .into_cosmos_msg(callback_address)
+            })
+            .transpose()
+            .map_err(Into::into)
     }
 }
 
 mod ibc_channel_close {
-    use super::{state, ContractError, DepsMut, IbcBasicResponse, IbcChannel};
+    use super::{
+        channel_close_callback, state, ChannelCloseReason, ContractError, DepsMut, Env,
+        IbcBasicResponse, IbcChannel,
+    };
 
     /// Handles the `ChanClosedInit` for the IBC module.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn init(deps: DepsMut, channel: IbcChannel) -> Result<IbcBasicResponse, ContractError> {
+    pub fn init(
+        deps: DepsMut,
+        env: Env,
+        channel: IbcChannel,
+    ) -> Result<IbcBasicResponse, ContractError> {
         if !state::ALLOW_CHANNEL_CLOSE_INIT
             .load(deps.storage)
             .unwrap_or_default()
         {
             return Err(ContractError::ChannelCloseInitNotAllowed);
-        };
+        }
 
         state::ALLOW_CHANNEL_CLOSE_INIT.save(deps.storage, &false)?;
 
         // Validate that this is the stored channel
-        let mut channel_state = state::CHANNEL_STATE.load(deps.storage)?;
+        let ica_id = state::load_channel_ica_id(deps.storage, &channel.endpoint.channel_id)?;
+        let mut channel_state = state::CHANNEL_STATE.load(deps.storage, ica_id)?;
         if channel_state.channel != channel {
             return Err(ContractError::InvalidChannelInContractState);
         }
@@ -231,17 +449,32 @@ mod ibc_channel_close {
 
         // Update the channel state
         channel_state.close();
-        state::CHANNEL_STATE.save(deps.storage, &channel_state)?;
+        state::CHANNEL_STATE.save(deps.storage, ica_id, &channel_state)?;
+        state::close_channel_record(
+            deps.storage,
+            ica_id,
+            &channel.endpoint.channel_id,
+            env.block.height,
+            ChannelCloseReason::CloseInit,
+        )?;
+
+        // make callback if needed
+        let callback_msg =
+            channel_close_callback(deps.storage, ica_id, channel, ChannelCloseReason::CloseInit)?;
 
-        // Return the response, emit events if needed
-        Ok(IbcBasicResponse::default())
+        Ok(IbcBasicResponse::default().add_submessages(callback_msg))
     }
 
     /// Handles the `ChanCloseConfirm` for the IBC module.
     #[allow(clippy::needless_pass_by_value)]
-    pub fn confirm(deps: DepsMut, channel: IbcChannel) -> Result<IbcBasicResponse, ContractError> {
+    pub fn confirm(
+        deps: DepsMut,
+        env: Env,
+        channel: IbcChannel,
+    ) -> Result<IbcBasicResponse, ContractError> {
         // Validate that this is the stored channel
-        let mut channel_state = state::CHANNEL_STATE.load(deps.storage)?;
+        let ica_id = state::load_channel_ica_id(deps.storage, &channel.endpoint.channel_id)?;
+        let mut channel_state = state::CHANNEL_STATE.load(deps.storage, ica_id)?;
         if channel_state.channel != channel {
             return Err(ContractError::InvalidChannelInContractState);
         }
@@ -254,9 +487,208 @@ mod ibc_channel_close {
 
         // Update the channel state
         channel_state.close();
-        state::CHANNEL_STATE.save(deps.storage, &channel_state)?;
+        state::CHANNEL_STATE.save(deps.storage, ica_id, &channel_state)?;
+        state::close_channel_record(
+            deps.storage,
+            ica_id,
+            &channel.endpoint.channel_id,
+            env.block.height,
+            ChannelCloseReason::CloseConfirm,
+        )?;
+
+        // make callback if needed
+        let callback_msg = channel_close_callback(
+            deps.storage,
+            ica_id,
+            channel,
+            ChannelCloseReason::CloseConfirm,
+        )?;
+
+        Ok(IbcBasicResponse::default().add_submessages(callback_msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{
+            mock_dependencies, mock_env, mock_ibc_channel, mock_ibc_channel_close_confirm,
+            mock_ibc_channel_connect_ack,
+        },
+        Addr, CosmosMsg, IbcOrder, WasmMsg,
+    };
+
+    use crate::{
+        ibc::types::metadata::TxEncoding,
+        types::{
+            msg::options::ChannelOpenInitOptions,
+            state::{ContractState, IcaInfo},
+        },
+    };
+
+    use super::*;
 
-        // Return the response, emit events if needed
-        Ok(IbcBasicResponse::default())
+    #[test]
+    fn test_open_ack_ica_address_continuity() {
+        let mut deps = mock_dependencies();
+
+        let address = |byte: u8| {
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cosmos").unwrap(), &[byte; 20])
+                .unwrap()
+        };
+
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-1", &0)
+            .unwrap();
+        state::ICA_INFO
+            .save(
+                &mut deps.storage,
+                0,
+                &IcaInfo::new(address(1), "channel-0", TxEncoding::Protobuf),
+            )
+            .unwrap();
+
+        let metadata = IcaMetadata::new(
+            keys::ICA_VERSION.to_string(),
+            "connection-2".to_string(),
+            "connection-3".to_string(),
+            address(2).to_uppercase(),
+            TxEncoding::Protobuf,
+            "sdk_multi_msg".to_string(),
+        );
+        let msg =
+            || mock_ibc_channel_connect_ack("channel-1", IbcOrder::Ordered, &metadata.to_string());
+
+        // A new address is rejected by default
+        let err = ibc_channel_connect(deps.as_mut(), mock_env(), msg()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::IcaAddressChanged {
+                expected: address(1),
+                actual: address(2),
+            }
+            .to_string()
+        );
+
+        // The owner allowed a new address
+        state::ALLOW_NEW_ICA_ADDRESS
+            .save(&mut deps.storage, 0, &true)
+            .unwrap();
+        ibc_channel_connect(deps.as_mut(), mock_env(), msg()).unwrap();
+
+        let ica_info = state::ICA_INFO.load(&deps.storage, 0).unwrap();
+        // The address is stored in lowercase
+        assert_eq!(ica_info.ica_address, address(2));
+        assert!(!state::ALLOW_NEW_ICA_ADDRESS.has(&deps.storage, 0));
+    }
+
+    #[test]
+    fn test_open_ack_tx_encoding_negotiation() {
+        let mut deps = mock_dependencies();
+
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-1", &0)
+            .unwrap();
+
+        let mut options = ChannelOpenInitOptions {
+            connection_id: "connection-2".to_string(),
+            counterparty_connection_id: "connection-3".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+        state::CHANNEL_OPEN_INIT_OPTIONS
+            .save(&mut deps.storage, 0, &options)
+            .unwrap();
+
+        // The host counter-proposes proto3json
+        let address =
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cosmos").unwrap(), &[1; 20])
+                .unwrap();
+        let metadata = IcaMetadata::new(
+            keys::ICA_VERSION.to_string(),
+            "connection-2".to_string(),
+            "connection-3".to_string(),
+            address,
+            TxEncoding::Proto3Json,
+            "sdk_multi_msg".to_string(),
+        );
+        let msg =
+            || mock_ibc_channel_connect_ack("channel-1", IbcOrder::Ordered, &metadata.to_string());
+
+        // Only protobuf is accepted by default
+        let err = ibc_channel_connect(deps.as_mut(), mock_env(), msg()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::UnsupportedPacketEncoding("\"proto3json\"".to_string()).to_string()
+        );
+
+        options.tx_encodings = vec![TxEncoding::Protobuf, TxEncoding::Proto3Json];
+        state::CHANNEL_OPEN_INIT_OPTIONS
+            .save(&mut deps.storage, 0, &options)
+            .unwrap();
+        ibc_channel_connect(deps.as_mut(), mock_env(), msg()).unwrap();
+
+        let ica_info = state::ICA_INFO.load(&deps.storage, 0).unwrap();
+        assert_eq!(ica_info.encoding, TxEncoding::Proto3Json);
+    }
+
+    #[test]
+    fn test_close_confirm_callback() {
+        let mut deps = mock_dependencies();
+
+        let channel = mock_ibc_channel("channel-0", IbcOrder::Ordered, "ics27-1");
+        state::STATE
+            .save(
+                &mut deps.storage,
+                &ContractState::new(Some(Addr::unchecked("callback"))),
+            )
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &1)
+            .unwrap();
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                1,
+                &ChannelState::new_open_channel(channel.clone()),
+            )
+            .unwrap();
+
+        let msg = mock_ibc_channel_close_confirm("channel-0", IbcOrder::Ordered, "ics27-1");
+        let res = ibc_channel_close(deps.as_mut(), mock_env(), msg).unwrap();
+
+        let expected_msg = IcaControllerCallbackMsg::OnChannelCloseCallback {
+            channel,
+            reason: ChannelCloseReason::CloseConfirm,
+            ica_id: 1,
+        }
+        .into_json_binary()
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].id,
+            crate::types::keys::reply_ids::CHANNEL_CLOSE_CALLBACK
+        );
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "callback".to_string(),
+                msg: expected_msg,
+                funds: vec![],
+            })
+        );
+        assert!(!state::CHANNEL_STATE
+            .load(&deps.storage, 1)
+            .unwrap()
+            .is_open());
     }
 }
@@ -2,16 +2,38 @@
 //! - The IBC packet acknowledgement.
 //! - The IBC packet timeout.
 //! - The IBC packet receive.
+//! - The IBC source callbacks of the controller module backend.
+//!
+//! It also contains the helpers that send and resend the ICA packets.
 
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_json, DepsMut, Env, IbcBasicResponse, IbcPacketAckMsg, IbcPacketReceiveMsg,
-    IbcPacketTimeoutMsg, IbcReceiveResponse, Never,
+    from_json, to_json_binary, Addr, Binary, CosmosMsg, DepsMut, Env, HexBinary, IbcBasicResponse,
+    IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg, IbcReceiveResponse,
+    IbcSourceCallbackMsg, IbcTimeout, ReplyOn, Storage, SubMsg,
 };
+use sha2::{Digest, Sha256};
 
-use crate::types::{query_msg, state, ContractError};
+use crate::types::{
+    callbacks::IcaControllerCallbackMsg,
+    keys,
+    msg::options::IcaBackend,
+    query_msg,
+    state::{
+        self,
+        ica_query::QueryPacket,
+        packet::{PacketStatus, PendingPacket},
+        queue::Correlation,
+        retry::{PacketRetry, ScheduledRetry, TimedOutPacket},
+    },
+    ContractError,
+};
 
-use super::types::{events, packet::acknowledgement::Data as AcknowledgementData};
+use super::types::{
+    events,
+    packet::{acknowledgement::Data as AcknowledgementData, IcaPacketData},
+    stargate::controller,
+};
 
 /// Implements the IBC module's `OnAcknowledgementPacket` handler.
 ///
@@ -20,16 +42,32 @@ use super::types::{events, packet::acknowledgement::Data as AcknowledgementData}
 /// This function returns an error if:
 ///
 /// - The acknowledgement data is invalid.
+/// - The outcome of the packet cannot be recorded.
 /// - [`ibc_packet_ack::success`] or [`ibc_packet_ack::error`] returns an error.
 #[entry_point]
 #[allow(clippy::needless_pass_by_value)]
 pub fn ibc_packet_ack(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     ack: IbcPacketAckMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
+    let data = from_json(&ack.acknowledgement.data)?;
+    let (status, error) = match &data {
+        AcknowledgementData::Result(_) => (PacketStatus::Success, None),
+        AcknowledgementData::Error(err) => (PacketStatus::Error, Some(err.clone())),
+    };
+    record_packet_outcome(
+        deps.storage,
+        &env,
+        &ack.original_packet,
+        &ack.relayer,
+        status,
+        error,
+        Some(&ack.acknowledgement.data),
+    )?;
+
     // This lets the ICA controller know whether or not the sent transactions succeeded.
-    match from_json(&ack.acknowledgement.data)? {
+    match data {
         AcknowledgementData::Result(res) => {
             ibc_packet_ack::success(deps, ack.original_packet, ack.relayer, res)
         }
@@ -47,43 +85,403 @@ pub fn ibc_packet_ack(
 ///
 /// - [`ibc_packet_timeout::callback`] returns an error.
 /// - The channel state cannot be loaded or saved.
+/// - The outcome of the packet cannot be recorded.
 #[entry_point]
 #[allow(clippy::needless_pass_by_value)]
 pub fn ibc_packet_timeout(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     msg: IbcPacketTimeoutMsg,
 ) -> Result<IbcBasicResponse, ContractError> {
-    // If the channel is ordered, close it.
-    let mut channel_state = state::CHANNEL_STATE.load(deps.storage)?;
-    if channel_state.is_ordered() {
+    // If the channel is ordered, close it, and reopen it if the ICA's policy allows it.
+    // This is only done for the current open channel of the ICA, so that a late timeout does not
+    // close the channel again, and a timeout on a previous channel does not close the new one.
+    let ica_id = state::load_channel_ica_id(deps.storage, &msg.packet.src.channel_id)?;
+    let pending = record_packet_outcome(
+        deps.storage,
+        &env,
+        &msg.packet,
+        &msg.relayer,
+        PacketStatus::Timeout,
+        None,
+        None,
+    )?;
+    let key = (msg.packet.src.channel_id.as_str(), msg.packet.sequence);
+    let retry = state::PACKET_RETRIES.may_load(deps.storage, key)?;
+    state::PACKET_RETRIES.remove(deps.storage, key);
+
+    let mut channel_state = state::CHANNEL_STATE.load(deps.storage, ica_id)?;
+    let is_ordered = channel_state.is_ordered();
+    let is_current_open = channel_state.channel.endpoint.channel_id == msg.packet.src.channel_id
+        && channel_state.is_open();
+    let (close_callback, reopen, dropped_retries) = if is_ordered && is_current_open {
         channel_state.close();
-        state::CHANNEL_STATE.save(deps.storage, &channel_state)?;
+        state::CHANNEL_STATE.save(deps.storage, ica_id, &channel_state)?;
+        state::close_channel_record(
+            deps.storage,
+            ica_id,
+            &msg.packet.src.channel_id,
+            env.block.height,
+            state::ChannelCloseReason::Timeout,
+        )?;
+        let close_callback = super::handshake::channel_close_callback(
+            deps.storage,
+            ica_id,
+            channel_state.channel,
+            state::ChannelCloseReason::Timeout,
+        )?;
+        let reopen = ibc_packet_timeout::auto_reopen(
+            deps.storage,
+            &env,
+            ica_id,
+            &msg.packet.src.channel_id,
+        )?;
+        // The scheduled retries can only be resent on a reopened channel.
+        let dropped_retries = if reopen.is_none() {
+            ibc_packet_timeout::drop_scheduled_retries(deps.storage, ica_id)?
+        } else {
+            vec![]
+        };
+        (close_callback, reopen, dropped_retries)
+    } else {
+        (None, None, vec![])
+    };
+
+    // The packet is resent if its retry policy allows it, and the timeout callback is only sent
+    // once no retry is left. On ordered channels, it is resent once the channel is reopened.
+    let retries = retry.as_ref().map_or(0, |retry| retry.attempts);
+    let retry = retry
+        .filter(|retry| retry.can_retry() && (!is_ordered || reopen.is_some()))
+        .zip(pending);
+    let response = match retry {
+        Some((retry, pending)) => ibc_packet_timeout::retry(
+            deps.storage,
+            &env,
+            msg.packet,
+            msg.relayer,
+            pending,
+            retry,
+            is_ordered,
+        )?,
+        None => ibc_packet_timeout::callback(deps, ica_id, msg.packet, msg.relayer, retries)?,
+    };
+
+    // The channel close callback is sent after the timeout callbacks
+    let response = response
+        .add_messages(dropped_retries)
+        .add_submessages(close_callback);
+
+    Ok(match reopen {
+        Some((reopen_msg, reopen_event)) => {
+            response.add_submessage(reopen_msg).add_event(reopen_event)
+        }
+        None => response,
+    })
+}
+
+/// Removes the packet from the in-flight packets and records its outcome in the packet history.
+/// Returns the in-flight packet, if it was recorded.
+///
+/// The acknowledgement is stored as its SHA-256 hash, which is the acknowledgement commitment
+/// of the host chain.
+fn record_packet_outcome(
+    storage: &mut dyn Storage,
+    env: &Env,
+    packet: &IbcPacket,
+    relayer: &Addr,
+    status: PacketStatus,
+    error: Option<String>,
+    ack: Option<&Binary>,
+) -> Result<Option<state::packet::PendingPacket>, ContractError> {
+    let key = (packet.src.channel_id.as_str(), packet.sequence);
+    let pending = state::PENDING_PACKETS.may_load(storage, key)?;
+    state::PENDING_PACKETS.remove(storage, key);
+
+    let ica_id = match &pending {
+        Some(pending) => pending.ica_id,
+        None => state::load_channel_ica_id(storage, &packet.src.channel_id)?,
+    };
+    let outcome = state::packet::PacketOutcome {
+        ica_id,
+        channel_id: packet.src.channel_id.clone(),
+        sequence: packet.sequence,
+        status,
+        error,
+        ack_hash: ack.map(|ack| HexBinary::from(Sha256::digest(ack.as_slice()).as_slice())),
+        relayer: relayer.clone(),
+        completion_height: env.block.height,
+        packet: pending,
+    };
+
+    state::record_packet_outcome(storage, &outcome)?;
+
+    Ok(outcome.packet)
+}
+
+/// Handles the source callbacks of the packets sent with the
+/// [`IcaBackend::ControllerModule`](crate::types::msg::options::IcaBackend) backend.
+///
+/// The `ibc-go` callbacks middleware calls this entry point when a packet sent with `MsgSendTx`
+/// is acknowledged or times out. They are handled like the [`ibc_packet_ack`] and
+/// [`ibc_packet_timeout`] entry points of the wasm port backend, so that the same callbacks are
+/// sent to the callback address.
+///
+/// # Errors
+///
+/// This function returns an error if the contract does not use the controller module backend, if
+/// the packet does not belong to any ICA of this contract, or if [`ibc_packet_ack`] or
+/// [`ibc_packet_timeout`] returns an error.
+#[entry_point]
+#[allow(clippy::needless_pass_by_value)]
+pub fn ibc_source_callback(
+    deps: DepsMut,
+    env: Env,
+    msg: IbcSourceCallbackMsg,
+) -> Result<IbcBasicResponse, ContractError> {
+    // With the wasm port backend, the packet lifecycle is already handled by the ibc entry points.
+    if state::STATE.load(deps.storage)?.backend != IcaBackend::ControllerModule {
+        return Err(ContractError::UnexpectedSourceCallback);
     }
 
-    ibc_packet_timeout::callback(deps, msg.packet, msg.relayer)
+    match msg {
+        IbcSourceCallbackMsg::Acknowledgement(ack) => ibc_packet_ack(
+            deps,
+            env,
+            IbcPacketAckMsg::new(ack.acknowledgement, ack.original_packet, ack.relayer),
+        ),
+        IbcSourceCallbackMsg::Timeout(timeout) => ibc_packet_timeout(
+            deps,
+            env,
+            IbcPacketTimeoutMsg::new(timeout.packet, timeout.relayer),
+        ),
+    }
 }
 
 /// Implements the IBC module's `OnRecvPacket` handler.
 ///
-/// # Panics
-/// Always panics because the ICA controller cannot receive packets.
+/// The ICA controller cannot receive packets, so every packet is rejected with an error
+/// acknowledgement. It must be implemented to satisfy the wasmd interface.
+///
+/// # Errors
+///
+/// This function returns an error if the acknowledgement cannot be serialized.
 #[entry_point]
-#[allow(clippy::needless_pass_by_value, clippy::missing_errors_doc)]
+#[allow(clippy::needless_pass_by_value)]
 pub fn ibc_packet_receive(
     _deps: DepsMut,
     _env: Env,
-    _msg: IbcPacketReceiveMsg,
-) -> Result<IbcReceiveResponse, Never> {
-    // An ICA controller cannot receive packets, so this is a panic.
-    // It must be implemented to satisfy the wasmd interface.
-    unreachable!("ICA controller cannot receive packets")
+    msg: IbcPacketReceiveMsg,
+) -> Result<IbcReceiveResponse, ContractError> {
+    let err = ContractError::CannotReceivePackets.to_string();
+    let ack = to_json_binary(&AcknowledgementData::Error(err.clone()))?;
+
+    Ok(IbcReceiveResponse::new(ack).add_event(events::packet_receive::error(&msg.packet, &err)))
+}
+
+/// Creates the [`SubMsg`] that resends a packet that timed out, on the current channel of
+/// the ICA. The retry keeps the sender, queries, subscription and correlation ids of the
+/// packet, and its timeout is extended by the backoff of the retry policy.
+///
+/// The resend is also replied to on error, so that a failed resend sends the timeout callback
+/// of the packet instead of reverting the transaction.
+///
+/// # Errors
+///
+/// Returns an error if the ICA info cannot be loaded or the packet cannot be sent.
+pub(crate) fn resend_packet_submsg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    ica_id: u64,
+    scheduled: ScheduledRetry,
+) -> Result<SubMsg, ContractError> {
+    let ica_info = state::load_ica_info(storage, ica_id)?;
+    let retry = PacketRetry {
+        attempts: scheduled.retry.attempts + 1,
+        ..scheduled.retry
+    };
+    let timeout =
+        IbcTimeout::with_timestamp(env.block.time.plus_seconds(retry.current_timeout_seconds()));
+
+    let submsg = send_packet_submsg(
+        storage,
+        env,
+        &scheduled.sender,
+        ica_id,
+        ica_info.channel_id,
+        &retry.packet_data.clone(),
+        &scheduled.paths,
+        timeout,
+        scheduled.subscription_id,
+        scheduled.correlations,
+        Some(retry),
+    )?;
+    let query_id: u64 = from_json(&submsg.payload)?;
+    state::RESENT_PACKETS.save(storage, query_id, &scheduled.timed_out)?;
+
+    Ok(SubMsg {
+        id: keys::reply_ids::RESEND_PACKET,
+        reply_on: ReplyOn::Always,
+        ..submsg
+    })
+}
+
+/// Creates the timeout callback of a packet that is not resent, if a callback address is set.
+///
+/// # Errors
+///
+/// Returns an error if the contract state cannot be loaded or the callback cannot be serialized.
+pub(crate) fn timeout_callback_msg(
+    storage: &dyn Storage,
+    ica_id: u64,
+    timed_out: TimedOutPacket,
+    correlations: Vec<Correlation>,
+    retries: u32,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let Some(contract_addr) = state::STATE.load(storage)?.callback_address else {
+        return Ok(None);
+    };
+
+    let callback_msg = IcaControllerCallbackMsg::OnTimeoutPacketCallback {
+        original_packet: timed_out.packet,
+        relayer: timed_out.relayer,
+        ica_id,
+        correlations,
+        retries,
+    }
+    .into_cosmos_msg(contract_addr)?;
+
+    Ok(Some(callback_msg))
+}
+
+/// Creates the [`SubMsg`]s that resend the packets of the ICA that timed out on its previous
+/// ordered channel, once the channel is reopened.
+///
+/// # Errors
+///
+/// Returns an error if the scheduled retries cannot be loaded or a packet cannot be sent.
+pub(crate) fn scheduled_retry_submsgs(
+    storage: &mut dyn Storage,
+    env: &Env,
+    ica_id: u64,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let scheduled = state::SCHEDULED_RETRIES
+        .may_load(storage, ica_id)?
+        .unwrap_or_default();
+    state::SCHEDULED_RETRIES.remove(storage, ica_id);
+
+    scheduled
+        .into_iter()
+        .map(|scheduled| resend_packet_submsg(storage, env, ica_id, scheduled))
+        .collect()
+}
+
+/// Creates the [`SubMsg`] that sends the ICA packet.
+/// The packet, its query paths, the subscription id, the correlation ids and the retry policy
+/// are saved until the reply is received, which records the packet in the pending packets.
+///
+/// # Errors
+///
+/// Returns an error if the packet cannot be encoded or the contract state cannot be loaded or saved.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn send_packet_submsg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    ica_id: u64,
+    channel_id: String,
+    ica_packet: &IcaPacketData,
+    query_paths: &[(String, bool)],
+    timeout: IbcTimeout,
+    subscription_id: Option<u64>,
+    correlations: Vec<Correlation>,
+    retry: Option<PacketRetry>,
+) -> Result<SubMsg, ContractError> {
+    let encoding = state::load_ica_info(storage, ica_id)?.encoding;
+    let packet = PendingPacket {
+        ica_id,
+        channel_id: channel_id.clone(),
+        sequence: 0,
+        sender: sender.clone(),
+        type_urls: ica_packet.type_urls(&encoding)?,
+        memo: ica_packet.memo.clone(),
+        timeout: timeout.clone(),
+        send_height: env.block.height,
+    };
+    let send_packet_msg = send_packet_msg(storage, env, ica_id, &channel_id, ica_packet, timeout)?;
+
+    // The packet is keyed by a unique id which is passed as the payload,
+    // so that multiple packets can be sent in the same transaction.
+    let query_id = state::NEXT_QUERY_ID.may_load(storage)?.unwrap_or_default();
+    state::NEXT_QUERY_ID.save(storage, &(query_id + 1))?;
+    state::QUERY.save(
+        storage,
+        query_id,
+        &QueryPacket {
+            packet,
+            channel_id,
+            paths: query_paths.to_vec(),
+            subscription_id,
+            correlations,
+            retry,
+        },
+    )?;
+
+    Ok(
+        SubMsg::reply_on_success(send_packet_msg, keys::reply_ids::SEND_PACKET)
+            .with_payload(to_json_binary(&query_id)?),
+    )
+}
+
+/// Creates the message that sends the ICA packet, according to the backend of the contract.
+///
+/// With the [`IcaBackend::ControllerModule`] backend, the packet is sent with `MsgSendTx`, and
+/// its memo requests the source callbacks that report the acknowledgement or the timeout.
+/// The controller module only supports timestamp timeouts.
+fn send_packet_msg(
+    storage: &dyn Storage,
+    env: &Env,
+    ica_id: u64,
+    channel_id: &str,
+    ica_packet: &IcaPacketData,
+    timeout: IbcTimeout,
+) -> Result<CosmosMsg, ContractError> {
+    match state::STATE.load(storage)?.backend {
+        IcaBackend::WasmPort => Ok(ica_packet
+            .to_ibc_msg_with_timeout(channel_id, timeout)?
+            .into()),
+        IcaBackend::ControllerModule => {
+            let Some(timestamp) = timeout.timestamp().filter(|_| timeout.block().is_none()) else {
+                return Err(ContractError::UnsupportedByControllerModule(
+                    "Block height timeout".to_string(),
+                ));
+            };
+            let options = state::CHANNEL_OPEN_INIT_OPTIONS.load(storage, ica_id)?;
+            let memo = controller::source_callback_memo(
+                env.contract.address.clone(),
+                ica_packet.memo.as_deref(),
+            )?;
+            let ica_packet = IcaPacketData {
+                memo: Some(memo),
+                ..ica_packet.clone()
+            };
+
+            Ok(controller::new_send_tx_msg(
+                env.contract.address.as_str(),
+                options.connection_id,
+                &ica_packet,
+                timestamp.nanos().saturating_sub(env.block.time.nanos()),
+            ))
+        }
+    }
 }
 
 mod ibc_packet_ack {
-    use cosmwasm_std::{Addr, Binary, IbcPacket};
+    use cosmos_sdk_proto::{
+        ibc::applications::interchain_accounts::host::v1::QueryParamsResponse, prost::Message,
+    };
+    use cosmwasm_std::{Addr, Binary, IbcPacket, Storage};
 
-    use crate::types::callbacks::IcaControllerCallbackMsg;
+    use crate::types::{callbacks::IcaControllerCallbackMsg, state::ica_query::SubscriptionResult};
 
     use super::{
         events, query_msg, state, AcknowledgementData, ContractError, DepsMut, IbcBasicResponse,
@@ -98,6 +496,7 @@ mod ibc_packet_ack {
         relayer: Addr,
         res: Binary,
     ) -> Result<IbcBasicResponse, ContractError> {
+        let ica_id = state::load_channel_ica_id(deps.storage, &packet.src.channel_id)?;
         let success_event = events::packet_ack::success(&packet, &res);
         let ica_acknowledgement = AcknowledgementData::Result(res);
         let query_result = state::PENDING_QUERIES
@@ -106,6 +505,7 @@ mod ibc_packet_ack {
                 |paths| -> Result<query_msg::IcaQueryResult, ContractError> {
                     let resp_msg =
                         ica_acknowledgement.decode_module_query_safe_resp_last_index()?;
+                    cache_host_allow_messages(deps.storage, ica_id, &paths, &resp_msg)?;
                     Ok(query_msg::result_from_response(paths, &resp_msg))
                 },
             )
@@ -113,12 +513,22 @@ mod ibc_packet_ack {
 
         state::PENDING_QUERIES.remove(deps.storage, (&packet.src.channel_id, packet.sequence));
 
+        if let Some(query_result) = &query_result {
+            cache_subscription_result(deps.storage, &packet, query_result)?;
+        }
+
+        let correlations = state::take_pending_correlations(deps.storage, &packet)?;
+        let retries = state::take_packet_retries(deps.storage, &packet)?;
+
         if let Some(contract_addr) = state::STATE.load(deps.storage)?.callback_address {
             let callback_msg = IcaControllerCallbackMsg::OnAcknowledgementPacketCallback {
                 ica_acknowledgement,
                 original_packet: packet,
                 relayer,
                 query_result,
+                ica_id,
+                correlations,
+                retries,
             }
             .into_cosmos_msg(contract_addr)?;
 
@@ -130,6 +540,61 @@ mod ibc_packet_ack {
         }
     }
 
+    /// Caches the `allow_messages` of the ICA host if the packet queried the host's params.
+    /// Responses that cannot be decoded are ignored so that the acknowledgement does not fail.
+    fn cache_host_allow_messages(
+        storage: &mut dyn Storage,
+        ica_id: u64,
+        paths: &[(String, bool)],
+        resp_msg: &query_msg::proto::MsgModuleQuerySafeResponse,
+    ) -> Result<(), ContractError> {
+        let params = paths
+            .iter()
+            .zip(resp_msg.responses.iter())
+            .filter(|((path, _), _)| path == query_msg::constants::ICA_HOST_PARAMS)
+            .filter_map(|(_, resp)| QueryParamsResponse::decode(resp.as_slice()).ok())
+            .filter_map(|resp| resp.params)
+            .next_back();
+
+        if let Some(params) = params {
+            state::HOST_ALLOW_MESSAGES.save(storage, ica_id, &params.allow_messages)?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the result of a subscription packet if its subscription caches results.
+    /// The packet is no longer tracked as a subscription packet afterwards.
+    fn cache_subscription_result(
+        storage: &mut dyn Storage,
+        packet: &IbcPacket,
+        result: &query_msg::IcaQueryResult,
+    ) -> Result<(), ContractError> {
+        let key = (packet.src.channel_id.as_str(), packet.sequence);
+        let Some(subscription_id) = state::PENDING_SUBSCRIPTION_PACKETS.may_load(storage, key)?
+        else {
+            return Ok(());
+        };
+        state::PENDING_SUBSCRIPTION_PACKETS.remove(storage, key);
+
+        // The subscription may have been cancelled while the packet was in flight.
+        let cache_results = state::SUBSCRIPTIONS
+            .may_load(storage, subscription_id)?
+            .is_some_and(|subscription| subscription.cache_results);
+        if cache_results {
+            state::SUBSCRIPTION_RESULTS.save(
+                storage,
+                subscription_id,
+                &SubscriptionResult {
+                    sequence: packet.sequence,
+                    result: result.clone(),
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
     /// Handles the unsuccessful acknowledgement of an ica packet. This means that the
     /// transaction failed to execute on the host chain.
     #[allow(clippy::needless_pass_by_value)]
@@ -140,14 +605,27 @@ mod ibc_packet_ack {
         err: String,
     ) -> Result<IbcBasicResponse, ContractError> {
         let state = state::STATE.load(deps.storage)?;
+        let ica_id = state::load_channel_ica_id(deps.storage, &packet.src.channel_id)?;
         let error_event = events::packet_ack::error(&packet, &err);
 
+        state::PENDING_QUERIES.remove(deps.storage, (&packet.src.channel_id, packet.sequence));
+        cache_subscription_result(
+            deps.storage,
+            &packet,
+            &query_msg::IcaQueryResult::Error(err.clone()),
+        )?;
+        let correlations = state::take_pending_correlations(deps.storage, &packet)?;
+        let retries = state::take_packet_retries(deps.storage, &packet)?;
+
         if let Some(contract_addr) = state.callback_address {
             let callback_msg = IcaControllerCallbackMsg::OnAcknowledgementPacketCallback {
                 ica_acknowledgement: AcknowledgementData::Error(err.clone()),
                 original_packet: packet,
                 relayer,
                 query_result: Some(query_msg::IcaQueryResult::Error(err)),
+                ica_id,
+                correlations,
+                retries,
             }
             .into_cosmos_msg(contract_addr)?;
 
@@ -161,33 +639,422 @@ mod ibc_packet_ack {
 }
 
 mod ibc_packet_timeout {
-    use cosmwasm_std::{Addr, IbcPacket};
+    use cosmwasm_std::{Addr, CosmosMsg, Env, Event, IbcPacket, Storage, SubMsg};
+
+    use crate::{
+        ibc::{handshake::channel_open_submsg, types::events},
+        types::state::{
+            self,
+            packet::PendingPacket,
+            retry::{PacketRetry, ScheduledRetry, TimedOutPacket},
+        },
+    };
+
+    use super::{
+        resend_packet_submsg, timeout_callback_msg, ContractError, DepsMut, IbcBasicResponse,
+    };
+
+    /// Returns the message that reopens the channel of the ICA with its saved options,
+    /// if the ICA has an auto reopen policy and it has attempts left.
+    pub fn auto_reopen(
+        storage: &mut dyn Storage,
+        env: &Env,
+        ica_id: u64,
+        closed_channel_id: &str,
+    ) -> Result<Option<(SubMsg, Event)>, ContractError> {
+        let Some(policy) = state::AUTO_REOPEN_POLICY.may_load(storage, ica_id)? else {
+            return Ok(None);
+        };
+        let Some(options) = state::CHANNEL_OPEN_INIT_OPTIONS.may_load(storage, ica_id)? else {
+            return Ok(None);
+        };
+
+        // Guard against reopen loops, e.g. when the reopened channels keep timing out.
+        let attempt = state::AUTO_REOPEN_ATTEMPTS
+            .may_load(storage, ica_id)?
+            .unwrap_or_default()
+            + 1;
+        if attempt > policy.max_attempts {
+            return Ok(None);
+        }
+        state::AUTO_REOPEN_ATTEMPTS.save(storage, ica_id, &attempt)?;
+
+        let msg = channel_open_submsg(storage, env, ica_id, options)?;
+        let event = events::channel_reopen::auto_reopen(ica_id, closed_channel_id, attempt);
+
+        Ok(Some((msg, event)))
+    }
 
-    use crate::types::{callbacks::IcaControllerCallbackMsg, state};
+    /// Returns the timeout callbacks of the packets that were scheduled to be resent on the
+    /// reopened channel of the ICA, and removes them, since the channel is not reopened.
+    pub fn drop_scheduled_retries(
+        storage: &mut dyn Storage,
+        ica_id: u64,
+    ) -> Result<Vec<CosmosMsg>, ContractError> {
+        let scheduled = state::SCHEDULED_RETRIES
+            .may_load(storage, ica_id)?
+            .unwrap_or_default();
+        state::SCHEDULED_RETRIES.remove(storage, ica_id);
 
-    use super::{ContractError, DepsMut, IbcBasicResponse};
+        scheduled
+            .into_iter()
+            .filter_map(|scheduled| {
+                timeout_callback_msg(
+                    storage,
+                    ica_id,
+                    scheduled.timed_out,
+                    scheduled.correlations,
+                    scheduled.retry.attempts,
+                )
+                .transpose()
+            })
+            .collect()
+    }
+
+    /// Resends a packet that timed out, or schedules it to be resent once its ordered channel is
+    /// reopened. The in-flight state of the packet is moved to the retry.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn retry(
+        storage: &mut dyn Storage,
+        env: &Env,
+        packet: IbcPacket,
+        relayer: Addr,
+        pending: PendingPacket,
+        retry: PacketRetry,
+        is_ordered: bool,
+    ) -> Result<IbcBasicResponse, ContractError> {
+        let key = (packet.src.channel_id.as_str(), packet.sequence);
+        let paths = state::PENDING_QUERIES
+            .may_load(storage, key)?
+            .unwrap_or_default();
+        state::PENDING_QUERIES.remove(storage, key);
+        let subscription_id = state::PENDING_SUBSCRIPTION_PACKETS.may_load(storage, key)?;
+        state::PENDING_SUBSCRIPTION_PACKETS.remove(storage, key);
+        let correlations = state::take_pending_correlations(storage, &packet)?;
+
+        let event = events::packet_retry::retry(pending.ica_id, &packet, retry.attempts + 1);
+        let scheduled = ScheduledRetry {
+            retry,
+            sender: pending.sender,
+            paths,
+            subscription_id,
+            correlations,
+            timed_out: TimedOutPacket { packet, relayer },
+        };
+
+        if is_ordered {
+            let mut scheduled_retries = state::SCHEDULED_RETRIES
+                .may_load(storage, pending.ica_id)?
+                .unwrap_or_default();
+            scheduled_retries.push(scheduled);
+            state::SCHEDULED_RETRIES.save(storage, pending.ica_id, &scheduled_retries)?;
+
+            return Ok(IbcBasicResponse::default().add_event(event));
+        }
+
+        let resend_msg = resend_packet_submsg(storage, env, pending.ica_id, scheduled)?;
+        Ok(IbcBasicResponse::default()
+            .add_submessage(resend_msg)
+            .add_event(event))
+    }
 
     /// Handles the timeout callbacks.
     #[allow(clippy::needless_pass_by_value)]
     pub fn callback(
         deps: DepsMut,
+        ica_id: u64,
         packet: IbcPacket,
         relayer: Addr,
+        retries: u32,
     ) -> Result<IbcBasicResponse, ContractError> {
-        let state = state::STATE.load(deps.storage)?;
-
         state::PENDING_QUERIES.remove(deps.storage, (&packet.src.channel_id, packet.sequence));
+        state::PENDING_SUBSCRIPTION_PACKETS
+            .remove(deps.storage, (&packet.src.channel_id, packet.sequence));
+        let correlations = state::take_pending_correlations(deps.storage, &packet)?;
 
-        if let Some(contract_addr) = state.callback_address {
-            let callback_msg = IcaControllerCallbackMsg::OnTimeoutPacketCallback {
-                original_packet: packet,
-                relayer,
-            }
-            .into_cosmos_msg(contract_addr)?;
+        let callback_msg = timeout_callback_msg(
+            deps.storage,
+            ica_id,
+            TimedOutPacket { packet, relayer },
+            correlations,
+            retries,
+        )?;
 
-            Ok(IbcBasicResponse::default().add_message(callback_msg))
-        } else {
-            Ok(IbcBasicResponse::default())
-        }
+        Ok(IbcBasicResponse::default().add_messages(callback_msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{
+        testing::{
+            mock_dependencies, mock_env, mock_ibc_channel, mock_ibc_packet_recv,
+            mock_ibc_packet_timeout,
+        },
+        IbcOrder,
+    };
+
+    use crate::types::{
+        msg::options::{AutoReopenPolicy, ChannelOpenInitOptions},
+        state::{ChannelState, ContractState},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_timeout_auto_reopen() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let options = ChannelOpenInitOptions {
+            connection_id: "connection-2".to_string(),
+            counterparty_connection_id: "connection-3".to_string(),
+            counterparty_port_id: None,
+            channel_ordering: Some(IbcOrder::Ordered),
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+        state::CHANNEL_OPEN_INIT_OPTIONS
+            .save(&mut deps.storage, 1, &options)
+            .unwrap();
+        state::AUTO_REOPEN_POLICY
+            .save(&mut deps.storage, 1, &AutoReopenPolicy { max_attempts: 1 })
+            .unwrap();
+
+        let open_channel = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, channel_id: &str| {
+            state::CHANNEL_ICA_ID
+                .save(&mut deps.storage, channel_id, &1)
+                .unwrap();
+            state::CHANNEL_STATE
+                .save(
+                    &mut deps.storage,
+                    1,
+                    &ChannelState::new_open_channel(mock_ibc_channel(
+                        channel_id,
+                        IbcOrder::Ordered,
+                        "ics27-1",
+                    )),
+                )
+                .unwrap();
+        };
+        let timeout = |deps: &mut cosmwasm_std::OwnedDeps<_, _, _>, channel_id: &str| {
+            let msg = mock_ibc_packet_timeout(channel_id, &"data").unwrap();
+            ibc_packet_timeout(deps.as_mut(), env.clone(), msg).unwrap()
+        };
+        let is_open = |deps: &cosmwasm_std::OwnedDeps<_, _, _>| {
+            state::CHANNEL_STATE
+                .load(&deps.storage, 1)
+                .unwrap()
+                .is_open()
+        };
+
+        // The first timeout reopens the channel
+        open_channel(&mut deps, "channel-0");
+        let res = timeout(&mut deps, "channel-0");
+        assert!(!is_open(&deps));
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "auto_reopen_channel");
+
+        // A late timeout on the closed channel does not use up a reopen attempt
+        let res = timeout(&mut deps, "channel-0");
+        assert!(res.messages.is_empty());
+        assert!(res.events.is_empty());
+
+        // A late timeout on the previous channel does not close the new channel
+        open_channel(&mut deps, "channel-1");
+        timeout(&mut deps, "channel-0");
+        assert!(is_open(&deps));
+
+        // The attempts are exhausted since the handshake has not completed in between
+        let res = timeout(&mut deps, "channel-1");
+        assert!(!is_open(&deps));
+        assert!(res.messages.is_empty());
+        assert!(res.events.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_drops_scheduled_retries_without_reopen() {
+        use crate::types::msg::options::RetryPolicy;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let callback = deps.api.addr_make("callback");
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(Some(callback)))
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-1", &1)
+            .unwrap();
+        state::CHANNEL_STATE
+            .save(
+                &mut deps.storage,
+                1,
+                &ChannelState::new_open_channel(mock_ibc_channel(
+                    "channel-1",
+                    IbcOrder::Ordered,
+                    "ics27-1",
+                )),
+            )
+            .unwrap();
+
+        // A packet that timed out on the previous channel waits for the channel to be reopened
+        let scheduled = ScheduledRetry {
+            retry: PacketRetry {
+                policy: RetryPolicy {
+                    max_attempts: 2,
+                    timeout_backoff_seconds: 0,
+                },
+                attempts: 1,
+                timeout_seconds: 60,
+                packet_data: IcaPacketData::new(vec![], None),
+            },
+            sender: deps.api.addr_make("sender"),
+            paths: vec![],
+            subscription_id: None,
+            correlations: vec![],
+            timed_out: TimedOutPacket {
+                packet: mock_ibc_packet_timeout("channel-0", &"data")
+                    .unwrap()
+                    .packet,
+                relayer: deps.api.addr_make("relayer"),
+            },
+        };
+        state::SCHEDULED_RETRIES
+            .save(&mut deps.storage, 1, &vec![scheduled])
+            .unwrap();
+
+        // Without an auto reopen policy, its timeout callback is sent with the current one
+        let msg = mock_ibc_packet_timeout("channel-1", &"data").unwrap();
+        let res = ibc_packet_timeout(deps.as_mut(), env, msg).unwrap();
+        let callbacks = res
+            .messages
+            .iter()
+            .map(|submsg| match &submsg.msg {
+                CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { msg, .. }) => {
+                    String::from_utf8(msg.to_vec()).unwrap()
+                }
+                msg => panic!("unexpected message: {msg:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(callbacks.len(), 3);
+        assert!(callbacks[0].contains("on_timeout_packet_callback"));
+        assert!(callbacks[1].contains("on_timeout_packet_callback"));
+        assert!(callbacks[1].contains(r#""retries":1"#));
+        assert!(callbacks[2].contains("on_channel_close_callback"));
+        assert!(state::SCHEDULED_RETRIES.is_empty(&deps.storage));
+    }
+
+    #[test]
+    fn test_ack_returns_correlations() {
+        use cosmwasm_std::{Addr, Binary, CosmosMsg, IbcAcknowledgement, WasmMsg};
+
+        use crate::types::state::queue::Correlation;
+
+        let mut deps = mock_dependencies();
+
+        let callback_address = Addr::unchecked("callback");
+        state::STATE
+            .save(
+                &mut deps.storage,
+                &ContractState::new(Some(callback_address.clone())),
+            )
+            .unwrap();
+        state::CHANNEL_ICA_ID
+            .save(&mut deps.storage, "channel-0", &0)
+            .unwrap();
+
+        let ack = IbcAcknowledgement::new(
+            to_json_binary(&AcknowledgementData::Result(Binary::default())).unwrap(),
+        );
+        let msg = cosmwasm_std::testing::mock_ibc_packet_ack("channel-0", &"data", ack).unwrap();
+        let correlation = Correlation {
+            sender: Addr::unchecked("enqueuer"),
+            correlation_id: Some("correlation-0".to_string()),
+            first_message_index: 0,
+            message_count: 1,
+            first_query_index: 0,
+            query_count: 0,
+        };
+        let key = (
+            msg.original_packet.src.channel_id.as_str(),
+            msg.original_packet.sequence,
+        );
+        state::PENDING_CORRELATIONS
+            .save(&mut deps.storage, key, &vec![correlation.clone()])
+            .unwrap();
+
+        let res = ibc_packet_ack(deps.as_mut(), mock_env(), msg.clone()).unwrap();
+        let expected =
+            crate::types::callbacks::IcaControllerCallbackMsg::OnAcknowledgementPacketCallback {
+                ica_acknowledgement: AcknowledgementData::Result(Binary::default()),
+                original_packet: msg.original_packet.clone(),
+                relayer: msg.relayer.clone(),
+                query_result: None,
+                ica_id: 0,
+                correlations: vec![correlation],
+                retries: 0,
+            };
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: callback_address.to_string(),
+                msg: expected.into_json_binary().unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // The correlation ids are only returned once
+        let key = (
+            msg.original_packet.src.channel_id.as_str(),
+            msg.original_packet.sequence,
+        );
+        assert!(!state::PENDING_CORRELATIONS.has(&deps.storage, key));
+    }
+
+    #[test]
+    fn test_receive_packet_error_ack() {
+        let mut deps = mock_dependencies();
+
+        let msg = mock_ibc_packet_recv("channel-0", &"data").unwrap();
+        let res = ibc_packet_receive(deps.as_mut(), mock_env(), msg).unwrap();
+
+        let ack: AcknowledgementData = from_json(res.acknowledgement.unwrap()).unwrap();
+        assert_eq!(
+            ack,
+            AcknowledgementData::Error(ContractError::CannotReceivePackets.to_string())
+        );
+        assert_eq!(res.events.len(), 1);
+        assert_eq!(res.events[0].ty, "receive_packet");
+    }
+
+    #[test]
+    fn test_source_callback_requires_controller_module() {
+        use cosmwasm_std::{Addr, IbcTimeoutCallbackMsg};
+
+        let mut deps = mock_dependencies();
+
+        state::STATE
+            .save(&mut deps.storage, &ContractState::new(None))
+            .unwrap();
+
+        let packet = mock_ibc_packet_timeout("channel-0", &"data")
+            .unwrap()
+            .packet;
+        let msg = IbcSourceCallbackMsg::Timeout(IbcTimeoutCallbackMsg::new(
+            packet,
+            Addr::unchecked("relayer"),
+        ));
+        let err = ibc_source_callback(deps.as_mut(), mock_env(), msg).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::UnexpectedSourceCallback.to_string()
+        );
     }
 }
@@ -41,6 +41,109 @@ pub mod packet_ack {
     }
 }
 
+/// contains the events emitted when a packet is received.
+pub mod packet_receive {
+    use super::{attributes, Event, IbcPacket};
+
+    const EVENT_TYPE: &str = "receive_packet";
+
+    /// returns an event for a received packet that was rejected with an error acknowledgement.
+    #[must_use]
+    pub fn error(packet: &IbcPacket, err: &str) -> Event {
+        Event::new(EVENT_TYPE)
+            .add_attributes(attributes::from_packet(packet))
+            .add_attribute(attributes::DEST_PORT, packet.dest.port_id.clone())
+            .add_attribute(attributes::DEST_CHANNEL, packet.dest.channel_id.clone())
+            .add_attribute(attributes::ERROR, err)
+    }
+}
+
+/// contains the events emitted when a channel is reopened automatically.
+pub mod channel_reopen {
+    use super::{attributes, Event};
+
+    const EVENT_TYPE: &str = "auto_reopen_channel";
+
+    /// returns an event for an automatic channel reopen attempt after the channel was closed.
+    #[must_use]
+    pub fn auto_reopen(ica_id: u64, closed_channel_id: &str, attempt: u32) -> Event {
+        Event::new(EVENT_TYPE)
+            .add_attribute(attributes::ICA_ID, ica_id.to_string())
+            .add_attribute(attributes::CLOSED_CHANNEL, closed_channel_id)
+            .add_attribute(attributes::ATTEMPT, attempt.to_string())
+    }
+}
+
+/// contains the events emitted when a packet that timed out is resent.
+pub mod packet_retry {
+    use super::{attributes, Event, IbcPacket};
+
+    const EVENT_TYPE: &str = "retry_packet";
+    const FAILED_EVENT_TYPE: &str = "retry_packet_failed";
+
+    /// returns an event for a packet that timed out and is resent, either right away or once
+    /// its ordered channel is reopened.
+    #[must_use]
+    pub fn retry(ica_id: u64, packet: &IbcPacket, attempt: u32) -> Event {
+        Event::new(EVENT_TYPE)
+            .add_attributes(attributes::from_packet(packet))
+            .add_attribute(attributes::ICA_ID, ica_id.to_string())
+            .add_attribute(attributes::ATTEMPT, attempt.to_string())
+    }
+
+    /// returns an event for a packet that timed out and could not be resent, for example
+    /// because the channel of the ICA is gone.
+    #[must_use]
+    pub fn failed(ica_id: u64, packet: &IbcPacket, attempt: u32, err: &str) -> Event {
+        Event::new(FAILED_EVENT_TYPE)
+            .add_attributes(attributes::from_packet(packet))
+            .add_attribute(attributes::ICA_ID, ica_id.to_string())
+            .add_attribute(attributes::ATTEMPT, attempt.to_string())
+            .add_attribute(attributes::ERROR, err)
+    }
+}
+
+/// contains the events emitted when the query subscriptions are cranked.
+pub mod query_subscription {
+    use super::{attributes, Event};
+
+    const EVENT_TYPE: &str = "skip_query_subscription";
+
+    /// returns an event for a due subscription whose packet could not be sent, for example
+    /// because the ICA host no longer allows its queries.
+    #[must_use]
+    pub fn skipped(subscription_id: u64, ica_id: u64, err: &str) -> Event {
+        Event::new(EVENT_TYPE)
+            .add_attribute(attributes::SUBSCRIPTION_ID, subscription_id.to_string())
+            .add_attribute(attributes::ICA_ID, ica_id.to_string())
+            .add_attribute(attributes::ERROR, err)
+    }
+}
+
+/// contains the events emitted when a channel status is synced from the chain.
+pub mod channel_sync {
+    use crate::types::state::ChannelStatus;
+
+    use super::{attributes, Event};
+
+    const EVENT_TYPE: &str = "sync_channel";
+
+    /// returns an event for a channel status that was changed by a sync.
+    #[must_use]
+    pub fn status_changed(
+        ica_id: u64,
+        channel_id: &str,
+        old_status: &ChannelStatus,
+        new_status: &ChannelStatus,
+    ) -> Event {
+        Event::new(EVENT_TYPE)
+            .add_attribute(attributes::ICA_ID, ica_id.to_string())
+            .add_attribute(attributes::CHANNEL_ID, channel_id)
+            .add_attribute(attributes::OLD_STATUS, old_status.to_string())
+            .add_attribute(attributes::NEW_STATUS, new_status.to_string())
+    }
+}
+
 mod attributes {
     use super::IbcPacket;
     use cosmwasm_std::Attribute;
@@ -49,9 +152,19 @@ mod attributes {
     pub const SEQUENCE: &str = "packet_sequence";
     pub const SRC_PORT: &str = "packet_src_port";
     pub const SRC_CHANNEL: &str = "packet_src_channel";
+    pub const DEST_PORT: &str = "packet_dest_port";
+    pub const DEST_CHANNEL: &str = "packet_dest_channel";
 
     pub const ERROR: &str = "error";
 
+    pub const ICA_ID: &str = "ica_id";
+    pub const CLOSED_CHANNEL: &str = "closed_channel_id";
+    pub const ATTEMPT: &str = "attempt";
+    pub const CHANNEL_ID: &str = "channel_id";
+    pub const SUBSCRIPTION_ID: &str = "subscription_id";
+    pub const OLD_STATUS: &str = "old_status";
+    pub const NEW_STATUS: &str = "new_status";
+
     /// returns the attributes for uniquely identifying a packet.
     pub fn from_packet(packet: &IbcPacket) -> Vec<Attribute> {
         vec![
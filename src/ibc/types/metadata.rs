@@ -6,11 +6,12 @@
 //! handshake between this contract and the ICA host. It encodes key information about the
 //! interchain account.
 
+use bech32::{primitives::decode::CheckedHrpstring, Bech32};
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{Deps, IbcChannel};
 
 use crate::types::{
-    state::{CHANNEL_OPEN_INIT_OPTIONS, CHANNEL_STATE},
+    state::{is_host_port_allowed, CHANNEL_OPEN_INIT_OPTIONS, CHANNEL_STATE},
     ContractError,
 };
 
@@ -31,7 +32,7 @@ pub struct IcaMetadata {
     /// and the ICA host will fill it in later during the handshake.
     pub address: String,
     /// The encoding of the messages sent to the ICA host.
-    /// This contract only supports json encoding.
+    /// The contract only accepts the encodings listed in the channel open init options.
     pub encoding: TxEncoding,
     /// The type of transaction that is sent to the ICA host.
     /// There is currently only one supported type: `sdk_multi_msg`.
@@ -41,10 +42,10 @@ pub struct IcaMetadata {
 /// `TxEncoding` is the encoding of the transactions sent to the ICA host.
 #[cw_serde]
 pub enum TxEncoding {
-    /// `Protobuf` is the protobuf serialization of the CosmosSDK's Any.
+    /// `Protobuf` is the protobuf serialization of the `CosmosSDK`'s Any.
     #[serde(rename = "proto3")]
     Protobuf,
-    /// `Proto3Json` is the json serialization of the CosmosSDK's Any.
+    /// `Proto3Json` is the json serialization of the `CosmosSDK`'s Any.
     #[serde(rename = "proto3json")]
     Proto3Json,
 }
@@ -74,33 +75,43 @@ impl IcaMetadata {
     ///
     /// This is a fallback option if the ICA controller is not provided with the
     /// handshake version metadata by the relayer. It first tries to load the
-    /// previous version of the [`IcaMetadata`] from the store, and if it fails,
-    /// it uses the [`CHANNEL_OPEN_INIT_OPTIONS`] to create a new [`IcaMetadata`].
+    /// previous version of the [`IcaMetadata`] of the given ICA from the store, and
+    /// if it fails, it uses the [`CHANNEL_OPEN_INIT_OPTIONS`] to create a new [`IcaMetadata`].
     ///
     /// # Errors
     ///
     /// Returns an error if the previous version of the [`IcaMetadata`] cannot be loaded
-    /// from the store, and no [`CHANNEL_OPEN_INIT_OPTIONS`] are set in the store.
-    pub fn from_channel(deps: Deps, channel: &IbcChannel) -> Result<Self, ContractError> {
+    /// from the store, and no [`CHANNEL_OPEN_INIT_OPTIONS`] are set in the store, or if
+    /// the channel's counterparty port is not the allowed host port in the options.
+    pub fn from_channel(
+        deps: Deps,
+        ica_id: u64,
+        channel: &IbcChannel,
+    ) -> Result<Self, ContractError> {
         // If the the counterparty chain is using the fee middleware, and the this chain is not,
         // and the previous handshake was initiated with an empty version string, then the
         // previous version in the contract's channel state will be wrapped by the fee middleware,
         // and the IcaMetadata will not be able to be deserialized.
-        if let Ok(channel_state) = CHANNEL_STATE.load(deps.storage) {
+        if let Ok(channel_state) = CHANNEL_STATE.load(deps.storage, ica_id) {
             if let Ok(previous_metadata) = serde_json_wasm::from_str(&channel_state.channel.version)
             {
                 return Ok(previous_metadata);
             }
         }
 
-        let options = CHANNEL_OPEN_INIT_OPTIONS.load(deps.storage)?;
+        let options = CHANNEL_OPEN_INIT_OPTIONS.load(deps.storage, ica_id)?;
         if options.connection_id != channel.connection_id {
             return Err(ContractError::InvalidConnection);
         }
+        if options.counterparty_port_id() != channel.counterparty_endpoint.port_id
+            || !is_host_port_allowed(deps.storage, &channel.counterparty_endpoint.port_id)?
+        {
+            return Err(ContractError::InvalidHostPort);
+        }
 
         Ok(Self {
             version: ICA_VERSION.to_string(),
-            encoding: TxEncoding::Protobuf,
+            encoding: options.proposed_tx_encoding(),
             controller_connection_id: options.connection_id,
             // counterparty connection_id is not exposed to the contract, so we
             // use a stargate query to get it. Stargate queries are not universally
@@ -126,15 +137,12 @@ impl IcaMetadata {
         if self.controller_connection_id != channel.connection_id {
             return Err(ContractError::InvalidConnection);
         }
-        if !matches!(self.encoding, TxEncoding::Protobuf) {
-            return Err(ContractError::UnsupportedPacketEncoding(
-                self.encoding.to_string(),
-            ));
-        }
         // We cannot check the counterparty connection_id because it is not exposed to the contract
         if !self.address.is_empty() {
-            validate_ica_address(&self.address)?;
+            validate_ica_address(&self.address, None)?;
         }
+        // `sdk_multi_msg` is the only tx type that ibc-go defines, so it is not negotiated like the
+        // encoding, and any other tx type is rejected.
         if self.tx_type != "sdk_multi_msg" {
             return Err(ContractError::UnsupportedTxType(self.tx_type.clone()));
         }
@@ -154,17 +162,40 @@ impl std::fmt::Display for TxEncoding {
     }
 }
 
-/// Validates an ICA address
+/// Validates a bech32 ICA address, and returns it in lowercase.
+///
+/// If `expected_prefix` is provided, the human readable part of the address must match it.
 ///
 /// # Errors
 ///
-/// Returns an error if the address is too long or contains invalid characters.
-fn validate_ica_address(address: &str) -> Result<(), ContractError> {
+/// Returns an error if the address is too long, is not valid bech32, has an invalid checksum,
+/// has no data, or does not have the expected prefix.
+pub fn validate_ica_address(
+    address: &str,
+    expected_prefix: Option<&str>,
+) -> Result<String, ContractError> {
     const DEFAULT_MAX_LENGTH: usize = 128;
-    if address.len() > DEFAULT_MAX_LENGTH || !address.chars().all(char::is_alphanumeric) {
+    if address.len() > DEFAULT_MAX_LENGTH {
+        return Err(ContractError::InvalidIcaAddress);
+    }
+
+    let checked = CheckedHrpstring::new::<Bech32>(address)?;
+    if checked.byte_iter().next().is_none() {
         return Err(ContractError::InvalidIcaAddress);
     }
-    Ok(())
+
+    let prefix = checked.hrp().to_lowercase();
+    if let Some(expected) = expected_prefix {
+        if prefix != expected.to_lowercase() {
+            return Err(ContractError::InvalidIcaAddressPrefix {
+                expected: expected.to_string(),
+                actual: prefix,
+            });
+        }
+    }
+
+    // Mixed case is rejected by the decoder, so this is the canonical form of the address.
+    Ok(address.to_lowercase())
 }
 
 #[cfg(test)]
@@ -222,20 +253,23 @@ mod tests {
             "channel-0",
             "port-0",
             "channel-1",
-            "port-1",
+            super::super::keys::HOST_PORT_ID,
         );
         let stored_init_options = ChannelOpenInitOptions {
             connection_id: "connection-0".to_string(),
             counterparty_connection_id: "connection-1".to_string(),
             counterparty_port_id: Some(super::super::keys::HOST_PORT_ID.to_string()),
             channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
         CHANNEL_OPEN_INIT_OPTIONS
-            .save(deps.as_mut().storage, &stored_init_options)
+            .save(deps.as_mut().storage, 0, &stored_init_options)
             .unwrap();
 
-        let metadata = IcaMetadata::from_channel(deps.as_ref(), &channel).unwrap();
+        let metadata = IcaMetadata::from_channel(deps.as_ref(), 0, &channel).unwrap();
 
         assert!(metadata.validate(&channel).is_ok());
     }
@@ -250,7 +284,7 @@ mod tests {
             "channel-0",
             "port-0",
             "channel-1",
-            "port-1",
+            super::super::keys::HOST_PORT_ID,
         );
 
         let stored_init_options = ChannelOpenInitOptions {
@@ -258,10 +292,13 @@ mod tests {
             counterparty_connection_id: "connection-1".to_string(),
             counterparty_port_id: Some(super::super::keys::HOST_PORT_ID.to_string()),
             channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
         };
 
         CHANNEL_OPEN_INIT_OPTIONS
-            .save(deps.as_mut().storage, &stored_init_options)
+            .save(deps.as_mut().storage, 0, &stored_init_options)
             .unwrap();
 
         let channel_2 = mock_channel(
@@ -270,12 +307,88 @@ mod tests {
             "channel-0",
             "port-0",
             "channel-1",
-            "port-1",
+            super::super::keys::HOST_PORT_ID,
         );
-        let metadata = IcaMetadata::from_channel(deps.as_ref(), &channel_1).unwrap();
+        let metadata = IcaMetadata::from_channel(deps.as_ref(), 0, &channel_1).unwrap();
         assert!(metadata.validate(&channel_2).is_err());
     }
 
+    #[test]
+    fn test_from_channel_host_port() {
+        let mut deps = mock_dependencies();
+
+        let channel = mock_channel(
+            "ics27-1",
+            "connection-0",
+            "channel-0",
+            "port-0",
+            "channel-1",
+            "wasm.host",
+        );
+        let stored_init_options = ChannelOpenInitOptions {
+            connection_id: "connection-0".to_string(),
+            counterparty_connection_id: "connection-1".to_string(),
+            counterparty_port_id: Some("wasm.host".to_string()),
+            channel_ordering: None,
+            expected_host_chain_id: None,
+            expected_address_prefix: None,
+            tx_encodings: vec![],
+        };
+
+        CHANNEL_OPEN_INIT_OPTIONS
+            .save(deps.as_mut().storage, 0, &stored_init_options)
+            .unwrap();
+
+        // The port is rejected until it is allowed
+        let err = IcaMetadata::from_channel(deps.as_ref(), 0, &channel).unwrap_err();
+        assert_eq!(err.to_string(), ContractError::InvalidHostPort.to_string());
+
+        crate::types::state::ALLOWED_HOST_PORTS
+            .save(deps.as_mut().storage, &vec!["wasm.host".to_string()])
+            .unwrap();
+        let metadata = IcaMetadata::from_channel(deps.as_ref(), 0, &channel).unwrap();
+        assert!(metadata.validate(&channel).is_ok());
+    }
+
+    #[test]
+    fn test_validate_ica_address() {
+        let address =
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cosmos").unwrap(), &[1; 32])
+                .unwrap();
+
+        assert_eq!(
+            validate_ica_address(&address, Some("cosmos")).unwrap(),
+            address
+        );
+        assert_eq!(
+            validate_ica_address(&address.to_uppercase(), None).unwrap(),
+            address
+        );
+
+        // Wrong prefix
+        let err = validate_ica_address(&address, Some("osmo")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ContractError::InvalidIcaAddressPrefix {
+                expected: "osmo".to_string(),
+                actual: "cosmos".to_string(),
+            }
+            .to_string()
+        );
+
+        // Invalid checksum, mixed case, not bech32, and no data
+        let mut bad_checksum = address.clone();
+        bad_checksum.pop();
+        bad_checksum.push(if address.ends_with('q') { 'p' } else { 'q' });
+        assert!(validate_ica_address(&bad_checksum, None).is_err());
+        let mixed_case = format!("COSMOS{}", &address[6..]);
+        assert!(validate_ica_address(&mixed_case, None).is_err());
+        assert!(validate_ica_address("icaaddress", None).is_err());
+        let no_data =
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("cosmos").unwrap(), &[]).unwrap();
+        assert!(validate_ica_address(&no_data, None).is_err());
+    }
+
     #[test]
     fn test_to_string() {
         let metadata = mock_metadata();
@@ -8,7 +8,7 @@ use cosmwasm_std::{to_json_binary, CosmosMsg, Env, IbcMsg, IbcTimeout, StdError,
 pub use cosmos_sdk_proto::ibc::applications::interchain_accounts::v1::CosmosTx;
 use cosmos_sdk_proto::traits::Message;
 
-use crate::types::cosmos_msg::convert_to_proto_any;
+use crate::types::cosmos_msg::{convert_to_proto3json, convert_to_proto_any};
 
 use super::metadata::TxEncoding;
 
@@ -19,10 +19,11 @@ pub const DEFAULT_TIMEOUT_SECONDS: u64 = 600;
 ///
 /// Currently, the host only supports [protobuf](super::metadata::TxEncoding::Protobuf) or
 /// [proto3json](super::metadata::TxEncoding::Proto3Json) serialized Cosmos transactions.
-/// This contract only supports the protobuf encoding.
+/// The encoding is negotiated with the host during the channel handshake.
 ///
 /// When protobuf is used, then the raw transaction must encoded using
 /// [`CosmosTx`](cosmos_sdk_proto::ibc::applications::interchain_accounts::v1::CosmosTx).
+/// When proto3json is used, then the raw transaction is the json encoding of the `CosmosTx`.
 #[allow(clippy::module_name_repetitions)]
 #[cw_serde]
 pub struct IcaPacketData {
@@ -64,27 +65,63 @@ impl IcaPacketData {
         Self::new(data, memo)
     }
 
+    /// Creates a new [`IcaPacketData`] from a list of proto3json encoded `Any` messages
+    #[must_use]
+    pub fn from_json_msgs(messages: &[String], memo: Option<String>) -> Self {
+        let data = format!(r#"{{"messages":[{}]}}"#, messages.join(","));
+        Self::new(data.into_bytes(), memo)
+    }
+
     /// Creates a new [`IcaPacketData`] from a list of [`CosmosMsg`] messages
     ///
     /// # Errors
     ///
     /// Returns an error if the [`CosmosMsg`] cannot be serialized to [`cosmos_sdk_proto::Any`]
-    /// when using the [`TxEncoding::Protobuf`] encoding.
+    /// when using the [`TxEncoding::Protobuf`] encoding, or to json when using the
+    /// [`TxEncoding::Proto3Json`] encoding, or if a [`cosmwasm_std::QueryRequest`] is not
+    /// supported.
     ///
     /// # Panics
     ///
     /// Panics if the [`CosmosMsg`] is not supported.
     ///
-    /// The supported [`CosmosMsg`]s for [`TxEncoding::Protobuf`] are listed in [`convert_to_proto_any`].
+    /// The supported [`CosmosMsg`]s for [`TxEncoding::Protobuf`] are listed in [`convert_to_proto_any`],
+    /// and for [`TxEncoding::Proto3Json`] in [`convert_to_proto3json`].
     #[cfg(feature = "query")]
     pub fn from_cosmos_msgs(
-        #[cfg(feature = "export")] storage: &mut dyn cosmwasm_std::Storage,
         messages: Vec<CosmosMsg>,
         queries: Vec<cosmwasm_std::QueryRequest<cosmwasm_std::Empty>>,
         encoding: &TxEncoding,
         memo: Option<String>,
         ica_address: &str,
     ) -> StdResult<Self> {
+        Self::from_cosmos_msgs_with_query_paths(messages, queries, encoding, memo, ica_address)
+            .map(|(packet_data, _)| packet_data)
+    }
+
+    /// Creates a new [`IcaPacketData`] from a list of [`CosmosMsg`] messages and also
+    /// returns the gRPC paths of the queries, in order, together with a flag indicating
+    /// whether the query was a [`cosmwasm_std::QueryRequest::Stargate`] query.
+    ///
+    /// The paths are needed to decode the query responses in the acknowledgement.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`CosmosMsg`] cannot be serialized to [`cosmos_sdk_proto::Any`]
+    /// when using the [`TxEncoding::Protobuf`] encoding, or if a [`cosmwasm_std::QueryRequest`]
+    /// is not supported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the [`CosmosMsg`] is not supported.
+    #[cfg(feature = "query")]
+    pub fn from_cosmos_msgs_with_query_paths(
+        messages: Vec<CosmosMsg>,
+        queries: Vec<cosmwasm_std::QueryRequest<cosmwasm_std::Empty>>,
+        encoding: &TxEncoding,
+        memo: Option<String>,
+        ica_address: &str,
+    ) -> StdResult<(Self, Vec<(String, bool)>)> {
         match encoding {
             TxEncoding::Protobuf => {
                 use crate::types::query_msg;
@@ -97,23 +134,30 @@ impl IcaPacketData {
                     })
                     .collect::<StdResult<Vec<cosmos_sdk_proto::Any>>>()?;
 
+                let mut query_paths = vec![];
                 if !queries.is_empty() {
-                    let (abci_queries, _paths): (
+                    let (abci_queries, paths): (
                         Vec<query_msg::proto::AbciQueryRequest>,
                         Vec<(String, bool)>,
-                    ) = queries.into_iter().fold((vec![], vec![]), |mut acc, msg| {
-                        let (path, data, is_stargate) = query_msg::query_to_protobuf(msg);
-
-                        acc.1.push((path.clone(), is_stargate));
-                        acc.0
-                            .push(query_msg::proto::AbciQueryRequest { path, data });
-
-                        acc
-                    });
-
-                    #[cfg(feature = "export")]
-                    #[allow(clippy::used_underscore_binding)]
-                    crate::types::state::QUERY.save(storage, &_paths)?;
+                    ) = queries
+                        .into_iter()
+                        .map(|msg| {
+                            let (path, data, is_stargate) = query_msg::query_to_protobuf(msg)
+                                .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+                            Ok((
+                                query_msg::proto::AbciQueryRequest {
+                                    path: path.clone(),
+                                    data,
+                                },
+                                (path, is_stargate),
+                            ))
+                        })
+                        .collect::<StdResult<Vec<_>>>()?
+                        .into_iter()
+                        .unzip();
+
+                    query_paths = paths;
 
                     let query_msg = query_msg::proto::MsgModuleQuerySafe {
                         signer: ica_address.to_string(),
@@ -125,11 +169,55 @@ impl IcaPacketData {
                     })?);
                 }
 
-                Ok(Self::from_proto_anys(proto_anys, memo))
+                Ok((Self::from_proto_anys(proto_anys, memo), query_paths))
+            }
+            TxEncoding::Proto3Json => {
+                use crate::types::query_msg;
+
+                let mut json_msgs = messages
+                    .into_iter()
+                    .map(|msg| convert_to_proto3json(msg, ica_address.to_string()))
+                    .collect::<StdResult<Vec<String>>>()?;
+
+                let mut query_paths = vec![];
+                if !queries.is_empty() {
+                    let (requests, paths): (
+                        Vec<proto3json::AbciQueryRequest>,
+                        Vec<(String, bool)>,
+                    ) = queries
+                        .into_iter()
+                        .map(|msg| {
+                            let (path, data, is_stargate) = query_msg::query_to_protobuf(msg)
+                                .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+                            Ok((
+                                proto3json::AbciQueryRequest {
+                                    path: path.clone(),
+                                    data: data.into(),
+                                },
+                                (path, is_stargate),
+                            ))
+                        })
+                        .collect::<StdResult<Vec<_>>>()?
+                        .into_iter()
+                        .unzip();
+
+                    query_paths = paths;
+
+                    json_msgs.push(
+                        serde_json_wasm::to_string(&proto3json::MsgModuleQuerySafe {
+                            type_url: proto3json::MSG_MODULE_QUERY_SAFE_TYPE_URL,
+                            signer: ica_address.to_string(),
+                            requests,
+                        })
+                        .map_err(|e| {
+                            StdError::generic_err(format!("failed to convert query msg: {e}"))
+                        })?,
+                    );
+                }
+
+                Ok((Self::from_json_msgs(&json_msgs, memo), query_paths))
             }
-            TxEncoding::Proto3Json => StdResult::Err(StdError::generic_err(
-                "unsupported encoding: proto3json".to_string(),
-            )),
         }
     }
 
@@ -164,10 +252,42 @@ impl IcaPacketData {
 
                 Ok(Self::from_proto_anys(proto_anys, memo))
             }
-            TxEncoding::Proto3Json => StdResult::Err(StdError::generic_err(
-                "unsupported encoding: proto3json".to_string(),
-            )),
+            TxEncoding::Proto3Json => {
+                let json_msgs = messages
+                    .into_iter()
+                    .map(|msg| convert_to_proto3json(msg, ica_address.to_string()))
+                    .collect::<StdResult<Vec<String>>>()?;
+
+                Ok(Self::from_json_msgs(&json_msgs, memo))
+            }
+        }
+    }
+
+    /// Returns the type URLs of the messages in the [`IcaPacketData`], which is decoded with the
+    /// negotiated `encoding` of the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a [`CosmosTx`] in the given encoding.
+    pub fn type_urls(&self, encoding: &TxEncoding) -> StdResult<Vec<String>> {
+        if encoding == &TxEncoding::Proto3Json {
+            let cosmos_tx: proto3json::CosmosTx = serde_json_wasm::from_slice(&self.data)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            return Ok(cosmos_tx
+                .messages
+                .into_iter()
+                .map(|msg| msg.type_url)
+                .collect());
         }
+
+        let cosmos_tx = CosmosTx::decode(self.data.as_slice())
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+
+        Ok(cosmos_tx
+            .messages
+            .into_iter()
+            .map(|msg| msg.type_url)
+            .collect())
     }
 
     /// Creates an [`IbcMsg::SendPacket`] message from the [`IcaPacketData`]
@@ -185,14 +305,70 @@ impl IcaPacketData {
             .block
             .time
             .plus_seconds(timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS));
+        self.to_ibc_msg_with_timeout(channel_id, IbcTimeout::with_timestamp(timeout_timestamp))
+    }
+
+    /// Creates an [`IbcMsg::SendPacket`] message from the [`IcaPacketData`] with the given
+    /// [`IbcTimeout`], which may be a block height, a timestamp, or both.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the [`IcaPacketData`] cannot be serialized to JSON.
+    pub fn to_ibc_msg_with_timeout(
+        &self,
+        channel_id: impl Into<String>,
+        timeout: IbcTimeout,
+    ) -> StdResult<IbcMsg> {
         Ok(IbcMsg::SendPacket {
             channel_id: channel_id.into(),
             data: to_json_binary(&self)?,
-            timeout: IbcTimeout::with_timestamp(timeout_timestamp),
+            timeout,
         })
     }
 }
 
+/// Contains the proto3json types of the [`CosmosTx`] and the query message.
+mod proto3json {
+    #[cfg(feature = "query")]
+    use cosmwasm_std::Binary;
+    use serde::Deserialize;
+    #[cfg(feature = "query")]
+    use serde::Serialize;
+
+    /// The type URL of the `MsgModuleQuerySafe` message.
+    #[cfg(feature = "query")]
+    pub const MSG_MODULE_QUERY_SAFE_TYPE_URL: &str =
+        "/ibc.applications.interchain_accounts.host.v1.MsgModuleQuerySafe";
+
+    /// Only the type URLs of the messages are decoded.
+    #[derive(Deserialize)]
+    pub struct CosmosTx {
+        pub messages: Vec<Any>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Any {
+        #[serde(rename = "@type")]
+        pub type_url: String,
+    }
+
+    #[cfg(feature = "query")]
+    #[derive(Serialize)]
+    pub struct MsgModuleQuerySafe {
+        #[serde(rename = "@type")]
+        pub type_url: &'static str,
+        pub signer: String,
+        pub requests: Vec<AbciQueryRequest>,
+    }
+
+    #[cfg(feature = "query")]
+    #[derive(Serialize)]
+    pub struct AbciQueryRequest {
+        pub path: String,
+        pub data: Binary,
+    }
+}
+
 /// contains the [`Data`] struct which is the acknowledgement to an ica packet
 pub mod acknowledgement {
     use cosmwasm_std::Binary;
@@ -303,4 +479,30 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_proto3json_packet_data() {
+        let messages = vec![CosmosMsg::Bank(cosmwasm_std::BankMsg::Send {
+            to_address: "receiver".to_string(),
+            amount: cosmwasm_std::coins(100, "stake"),
+        })];
+        let packet_data = IcaPacketData::from_cosmos_msgs(
+            messages,
+            #[cfg(feature = "query")]
+            vec![],
+            &TxEncoding::Proto3Json,
+            None,
+            "ica",
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(packet_data.data.clone()).unwrap(),
+            r#"{"messages":[{"@type":"/cosmos.bank.v1beta1.MsgSend","from_address":"ica","to_address":"receiver","amount":[{"denom":"stake","amount":"100"}]}]}"#
+        );
+        assert_eq!(
+            packet_data.type_urls(&TxEncoding::Proto3Json).unwrap(),
+            vec!["/cosmos.bank.v1beta1.MsgSend".to_string()]
+        );
+    }
 }
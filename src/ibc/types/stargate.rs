@@ -5,15 +5,24 @@
 
 /// Contains the stargate channel lifecycle helper methods.
 pub mod channel {
-    use cosmwasm_std::{CosmosMsg, IbcOrder};
+    use cosmwasm_std::{Binary, CosmosMsg, IbcOrder, QuerierWrapper};
 
     use cosmos_sdk_proto::ibc::core::channel::v1::{
-        Channel, Counterparty, MsgChannelOpenInit, Order, State,
+        Channel, Counterparty, MsgChannelOpenInit, Order, QueryChannelRequest,
+        QueryChannelResponse, State,
     };
     use cosmos_sdk_proto::traits::Message;
 
+    use crate::types::{state::ChannelStatus, ContractError};
+
     use super::super::{keys, metadata};
 
+    /// The gRPC path of the query for a channel.
+    pub const CHANNEL_PATH: &str = "/ibc.core.channel.v1.Query/Channel";
+
+    const STATE_FLUSHING: i32 = 5;
+    const STATE_FLUSHCOMPLETE: i32 = 6;
+
     /// Creates a new [`MsgChannelOpenInit`] for an ica channel with the given contract address.
     ///
     /// Also generates the handshake version.
@@ -55,6 +64,90 @@ pub mod channel {
         }
     }
 
+    /// Queries the status of a channel on this chain. The query must be allowed by the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails or the response cannot be decoded.
+    pub fn query_channel_status(
+        querier: &QuerierWrapper,
+        port_id: impl Into<String>,
+        channel_id: impl Into<String>,
+    ) -> Result<ChannelStatus, ContractError> {
+        query_channel(querier, port_id, channel_id).map(|(status, _, _)| status)
+    }
+
+    /// Queries the status, the ordering and the version of a channel on this chain.
+    /// The query must be allowed by the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails or the response cannot be decoded.
+    pub fn query_channel(
+        querier: &QuerierWrapper,
+        port_id: impl Into<String>,
+        channel_id: impl Into<String>,
+    ) -> Result<(ChannelStatus, Option<IbcOrder>, String), ContractError> {
+        let request = QueryChannelRequest {
+            port_id: port_id.into(),
+            channel_id: channel_id.into(),
+        };
+        let response = querier.query_grpc(
+            CHANNEL_PATH.to_string(),
+            Binary::new(request.encode_to_vec()),
+        )?;
+
+        channel_from_response(&response)
+    }
+
+    /// Decodes the channel status from a protobuf encoded [`QueryChannelResponse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response cannot be decoded or has an unknown channel state.
+    pub fn channel_status_from_response(data: &[u8]) -> Result<ChannelStatus, ContractError> {
+        channel_from_response(data).map(|(status, _, _)| status)
+    }
+
+    /// Decodes the channel status, ordering and version from a protobuf encoded
+    /// [`QueryChannelResponse`]. The ordering is `None` if it is unspecified.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response cannot be decoded or has an unknown channel state.
+    pub fn channel_from_response(
+        data: &[u8],
+    ) -> Result<(ChannelStatus, Option<IbcOrder>, String), ContractError> {
+        let channel = QueryChannelResponse::decode(data)?
+            .channel
+            .ok_or_else(|| ContractError::EmptyResponse("channel".to_string()))?;
+
+        // The flushing states were added in ibc-go v8.1.0, and are not in the generated protos.
+        let status = match State::try_from(channel.state) {
+            Ok(State::UninitializedUnspecified) => ChannelStatus::Uninitialized,
+            Ok(State::Init) => ChannelStatus::Init,
+            Ok(State::Tryopen) => ChannelStatus::TryOpen,
+            Ok(State::Open) => ChannelStatus::Open,
+            Ok(State::Closed) => ChannelStatus::Closed,
+            Err(_) if channel.state == STATE_FLUSHING => ChannelStatus::Flushing,
+            Err(_) if channel.state == STATE_FLUSHCOMPLETE => ChannelStatus::FlushComplete,
+            Err(_) => {
+                return Err(ContractError::UnknownDataType(format!(
+                    "channel state {}",
+                    channel.state
+                )))
+            }
+        };
+
+        let ordering = match Order::try_from(channel.ordering) {
+            Ok(Order::Ordered) => Some(IbcOrder::Ordered),
+            Ok(Order::Unordered) => Some(IbcOrder::Unordered),
+            _ => None,
+        };
+
+        Ok((status, ordering, channel.version))
+    }
+
     /// Creates a new [`MsgChannelOpenInit`] for an ica channel.
     /// If the counterparty port id is not provided, [`keys::HOST_PORT_ID`] is used.
     fn new_msg_channel_open_init(
@@ -66,7 +159,7 @@ pub mod channel {
         ordering: Option<IbcOrder>,
     ) -> MsgChannelOpenInit {
         let counterparty_port_id =
-            counterparty_port_id.map_or(keys::HOST_PORT_ID.into(), Into::into);
+            counterparty_port_id.map_or_else(|| keys::HOST_PORT_ID.into(), Into::into);
 
         let ordering = ordering.map_or(Order::Ordered, |ordering| match ordering {
             IbcOrder::Ordered => Order::Ordered,
@@ -88,4 +181,336 @@ pub mod channel {
             signer: signer.into(),
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use cosmos_sdk_proto::ibc::core::channel::v1::QueryChannelResponse;
+
+        use super::*;
+
+        #[test]
+        fn test_channel_status_from_response() {
+            let encode = |state: i32| {
+                QueryChannelResponse {
+                    channel: Some(Channel {
+                        state,
+                        ..Default::default()
+                    }),
+                    proof: vec![],
+                    proof_height: None,
+                }
+                .encode_to_vec()
+            };
+
+            assert_eq!(
+                channel_status_from_response(&encode(State::Open.into())).unwrap(),
+                ChannelStatus::Open
+            );
+            assert_eq!(
+                channel_status_from_response(&encode(STATE_FLUSHING)).unwrap(),
+                ChannelStatus::Flushing
+            );
+            assert_eq!(
+                channel_status_from_response(&encode(STATE_FLUSHCOMPLETE)).unwrap(),
+                ChannelStatus::FlushComplete
+            );
+            assert!(channel_status_from_response(&encode(7)).is_err());
+        }
+    }
+}
+
+/// Contains the stargate connection query helper methods.
+pub mod connection {
+    use cosmwasm_std::{Binary, QuerierWrapper};
+
+    use cosmos_sdk_proto::ibc::core::connection::v1::{
+        QueryConnectionClientStateRequest, QueryConnectionClientStateResponse,
+    };
+    use cosmos_sdk_proto::ibc::lightclients::tendermint::v1::ClientState;
+    use cosmos_sdk_proto::traits::{Message, Name};
+
+    use crate::types::ContractError;
+
+    /// The gRPC path of the query for the client state of a connection.
+    pub const CONNECTION_CLIENT_STATE_PATH: &str =
+        "/ibc.core.connection.v1.Query/ConnectionClientState";
+
+    /// Queries the chain id of the counterparty chain of a connection from the client state
+    /// of the connection. The query must be allowed by the chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails, or if the client state is not a tendermint client state.
+    pub fn query_counterparty_chain_id(
+        querier: &QuerierWrapper,
+        connection_id: impl Into<String>,
+    ) -> Result<String, ContractError> {
+        let request = QueryConnectionClientStateRequest {
+            connection_id: connection_id.into(),
+        };
+        let response = querier.query_grpc(
+            CONNECTION_CLIENT_STATE_PATH.to_string(),
+            Binary::new(request.encode_to_vec()),
+        )?;
+
+        chain_id_from_client_state_response(&response)
+    }
+
+    /// Decodes the chain id from a protobuf encoded [`QueryConnectionClientStateResponse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response cannot be decoded, or if the client state is not
+    /// a tendermint client state.
+    pub fn chain_id_from_client_state_response(data: &[u8]) -> Result<String, ContractError> {
+        let client_state = QueryConnectionClientStateResponse::decode(data)?
+            .identified_client_state
+            .and_then(|identified| identified.client_state)
+            .ok_or_else(|| ContractError::EmptyResponse("client state".to_string()))?;
+
+        if client_state.type_url != ClientState::type_url() {
+            return Err(ContractError::UnsupportedClientState(client_state.type_url));
+        }
+
+        Ok(ClientState::decode(client_state.value.as_slice())?.chain_id)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use cosmos_sdk_proto::{ibc::core::client::v1::IdentifiedClientState, Any};
+
+        use super::*;
+
+        fn encode_response(client_state: Any) -> Vec<u8> {
+            QueryConnectionClientStateResponse {
+                identified_client_state: Some(IdentifiedClientState {
+                    client_id: "07-tendermint-0".to_string(),
+                    client_state: Some(client_state),
+                }),
+                proof: vec![],
+                proof_height: None,
+            }
+            .encode_to_vec()
+        }
+
+        #[test]
+        fn test_chain_id_from_client_state_response() {
+            let client_state = ClientState {
+                chain_id: "cosmoshub-4".to_string(),
+                ..Default::default()
+            };
+            let data = encode_response(Any {
+                type_url: ClientState::type_url(),
+                value: client_state.encode_to_vec(),
+            });
+            assert_eq!(
+                chain_id_from_client_state_response(&data).unwrap(),
+                "cosmoshub-4"
+            );
+
+            let data = encode_response(Any {
+                type_url: "/ibc.lightclients.wasm.v1.ClientState".to_string(),
+                value: vec![],
+            });
+            assert_eq!(
+                chain_id_from_client_state_response(&data)
+                    .unwrap_err()
+                    .to_string(),
+                ContractError::UnsupportedClientState(
+                    "/ibc.lightclients.wasm.v1.ClientState".to_string()
+                )
+                .to_string()
+            );
+        }
+    }
+}
+
+/// Contains the stargate messages of the `ibc-go` interchain accounts controller module, which
+/// are used by the [`IcaBackend::ControllerModule`](crate::types::msg::options::IcaBackend) backend.
+///
+/// These messages are not in the generated protos, so they are encoded with [`anybuf`].
+pub mod controller {
+    use anybuf::{Anybuf, Bufany};
+    use cosmwasm_std::{CosmosMsg, IbcCallbackRequest, IbcOrder, IbcSrcCallback};
+
+    use crate::{
+        ibc::types::{keys, metadata, packet::IcaPacketData},
+        types::{msg::options::ChannelOpenInitOptions, ContractError},
+    };
+
+    /// The type URL of `MsgRegisterInterchainAccount`.
+    pub const REGISTER_INTERCHAIN_ACCOUNT_TYPE_URL: &str =
+        "/ibc.applications.interchain_accounts.controller.v1.MsgRegisterInterchainAccount";
+    /// The type URL of `MsgSendTx`.
+    pub const SEND_TX_TYPE_URL: &str =
+        "/ibc.applications.interchain_accounts.controller.v1.MsgSendTx";
+    /// The prefix of the controller port of an owner.
+    pub const CONTROLLER_PORT_PREFIX: &str = "icacontroller-";
+
+    /// The values of the `ibc.core.channel.v1.Order` enum.
+    const ORDER_UNORDERED: u64 = 1;
+    const ORDER_ORDERED: u64 = 2;
+
+    /// Creates a `MsgRegisterInterchainAccount` that opens an ICA channel owned by the contract.
+    /// The version metadata is built from the options, like in `MsgChannelOpenInit`.
+    ///
+    /// The ordering is always sent, and defaults to [`IbcOrder::Ordered`] like in
+    /// `MsgChannelOpenInit`. This requires `ibc-go` v8.1.0 or later.
+    #[must_use]
+    pub fn new_register_interchain_account_msg(
+        owner: impl Into<String>,
+        options: &ChannelOpenInitOptions,
+    ) -> CosmosMsg {
+        let version = metadata::IcaMetadata::new(
+            keys::ICA_VERSION.into(),
+            options.connection_id.clone(),
+            options.counterparty_connection_id.clone(),
+            String::new(),
+            options.proposed_tx_encoding(),
+            "sdk_multi_msg".to_string(),
+        );
+
+        let ordering = match options.channel_ordering {
+            Some(IbcOrder::Unordered) => ORDER_UNORDERED,
+            Some(IbcOrder::Ordered) | None => ORDER_ORDERED,
+        };
+        let msg = Anybuf::new()
+            .append_string(1, owner.into())
+            .append_string(2, &options.connection_id)
+            .append_string(3, version.to_string())
+            .append_uint64(4, ordering);
+
+        #[allow(deprecated)]
+        CosmosMsg::Stargate {
+            type_url: REGISTER_INTERCHAIN_ACCOUNT_TYPE_URL.into(),
+            value: msg.into_vec().into(),
+        }
+    }
+
+    /// Creates a `MsgSendTx` that sends the packet data through the controller module.
+    /// The timeout is in nanoseconds, relative to the block time of this chain.
+    #[must_use]
+    pub fn new_send_tx_msg(
+        owner: impl Into<String>,
+        connection_id: impl Into<String>,
+        packet_data: &IcaPacketData,
+        relative_timeout: u64,
+    ) -> CosmosMsg {
+        let packet_data_msg = Anybuf::new()
+            .append_uint64(1, u64::from(packet_data.r#type))
+            .append_bytes(2, &packet_data.data)
+            .append_string(3, packet_data.memo.as_deref().unwrap_or_default());
+
+        let msg = Anybuf::new()
+            .append_string(1, owner.into())
+            .append_string(2, connection_id.into())
+            .append_message(3, &packet_data_msg)
+            .append_uint64(4, relative_timeout);
+
+        #[allow(deprecated)]
+        CosmosMsg::Stargate {
+            type_url: SEND_TX_TYPE_URL.into(),
+            value: msg.into_vec().into(),
+        }
+    }
+
+    /// Decodes the channel id and the port id from a `MsgRegisterInterchainAccountResponse`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response cannot be decoded or has no channel id.
+    pub fn register_interchain_account_response(
+        data: &[u8],
+    ) -> Result<(String, String), ContractError> {
+        let response = Bufany::deserialize(data)?;
+        let channel_id = response
+            .string(1)
+            .filter(|channel_id| !channel_id.is_empty())
+            .ok_or_else(|| ContractError::EmptyResponse("channel id".to_string()))?;
+        let port_id = response.string(2).unwrap_or_default();
+
+        Ok((channel_id, port_id))
+    }
+
+    /// Returns the packet memo that requests the source callbacks of the packet for the contract.
+    ///
+    /// A memo set by the owner must be a json object, and the `src_callback` key is added to it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the memo is not a json object or already has a `src_callback` key.
+    pub fn source_callback_memo(
+        contract_address: cosmwasm_std::Addr,
+        memo: Option<&str>,
+    ) -> Result<String, ContractError> {
+        let callback = serde_json_wasm::to_string(&IbcCallbackRequest::source(IbcSrcCallback {
+            address: contract_address,
+            gas_limit: None,
+        }))?;
+
+        let memo = memo.map(str::trim).unwrap_or_default();
+        if memo.is_empty() {
+            return Ok(callback);
+        }
+
+        // Only the keys of the memo are needed.
+        #[allow(clippy::zero_sized_map_values)]
+        let fields: std::collections::BTreeMap<String, serde::de::IgnoredAny> =
+            serde_json_wasm::from_str(memo)
+                .map_err(|_| ContractError::InvalidPacketMemo("must be a json object".into()))?;
+        if fields.contains_key("src_callback") {
+            return Err(ContractError::InvalidPacketMemo(
+                "src_callback is set by the contract".into(),
+            ));
+        }
+
+        // Both are json objects, so the callback's fields are prepended to the memo's fields.
+        let callback_fields = &callback[1..callback.len() - 1];
+        let memo_fields = &memo[1..];
+        if fields.is_empty() {
+            Ok(callback)
+        } else {
+            Ok(format!("{{{callback_fields},{memo_fields}"))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use cosmwasm_std::Addr;
+
+        use super::*;
+
+        #[test]
+        fn test_source_callback_memo() {
+            let contract = || Addr::unchecked("contract");
+
+            assert_eq!(
+                source_callback_memo(contract(), None).unwrap(),
+                r#"{"src_callback":{"address":"contract"}}"#
+            );
+            assert_eq!(
+                source_callback_memo(contract(), Some(r#"{"forward":{"port":"transfer"}}"#))
+                    .unwrap(),
+                r#"{"src_callback":{"address":"contract"},"forward":{"port":"transfer"}}"#
+            );
+            assert!(source_callback_memo(contract(), Some("hello")).is_err());
+            assert!(source_callback_memo(contract(), Some(r#"{"src_callback":{}}"#)).is_err());
+        }
+
+        #[test]
+        fn test_register_interchain_account_response() {
+            let data = Anybuf::new()
+                .append_string(1, "channel-7")
+                .append_string(2, "icacontroller-contract")
+                .into_vec();
+            assert_eq!(
+                register_interchain_account_response(&data).unwrap(),
+                (
+                    "channel-7".to_string(),
+                    "icacontroller-contract".to_string()
+                )
+            );
+            assert!(register_interchain_account_response(&[]).is_err());
+        }
+    }
 }
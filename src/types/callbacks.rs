@@ -8,8 +8,9 @@ use cosmwasm_std::{
     to_json_binary, Addr, Binary, CosmosMsg, IbcChannel, IbcPacket, StdResult, WasmMsg,
 };
 
-use crate::ibc::types::{
-    metadata::TxEncoding, packet::acknowledgement::Data as AcknowledgementData,
+use crate::{
+    ibc::types::{metadata::TxEncoding, packet::acknowledgement::Data as AcknowledgementData},
+    types::state::{queue::Correlation, ChannelCloseReason},
 };
 
 /// `IcaControllerCallbackMsg` is the type of message that this contract can send to other contracts.
@@ -37,6 +38,17 @@ pub enum IcaControllerCallbackMsg {
         #[cfg(feature = "query")]
         #[serde(skip_serializing_if = "Option::is_none")]
         query_result: Option<super::query_msg::IcaQueryResult>,
+        /// The id of the ICA that sent the packet.
+        #[serde(default)]
+        ica_id: u64,
+        /// The correlation ids of the queue entries, if the packet was sent by
+        /// [`ExecuteMsg::Flush`](crate::types::msg::ExecuteMsg::Flush).
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        correlations: Vec<Correlation>,
+        /// The number of times the packet was resent after timing out, if it was sent with a
+        /// [`RetryPolicy`](crate::types::msg::options::RetryPolicy).
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
     },
     /// `OnTimeoutPacketCallback` is the callback that this contract makes to other contracts
     /// when it receives a timeout packet.
@@ -45,6 +57,17 @@ pub enum IcaControllerCallbackMsg {
         original_packet: IbcPacket,
         /// The relayer that submitted acknowledgement packet
         relayer: Addr,
+        /// The id of the ICA that sent the packet.
+        #[serde(default)]
+        ica_id: u64,
+        /// The correlation ids of the queue entries, if the packet was sent by
+        /// [`ExecuteMsg::Flush`](crate::types::msg::ExecuteMsg::Flush).
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        correlations: Vec<Correlation>,
+        /// The number of times the packet was resent after timing out, if it was sent with a
+        /// [`RetryPolicy`](crate::types::msg::options::RetryPolicy).
+        #[serde(default, skip_serializing_if = "is_zero")]
+        retries: u32,
     },
     /// `OnChannelOpenAckCallback` is the callback that this contract makes to other contracts
     /// when it receives a channel open acknowledgement.
@@ -55,6 +78,20 @@ pub enum IcaControllerCallbackMsg {
         ica_address: String,
         /// The tx encoding this ICA channel uses.
         tx_encoding: TxEncoding,
+        /// The id of the ICA whose channel was opened.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `OnChannelCloseCallback` is the callback that this contract makes to other contracts
+    /// when the channel of an ICA is closed.
+    OnChannelCloseCallback {
+        /// The channel that was closed.
+        channel: IbcChannel,
+        /// The reason the channel was closed.
+        reason: ChannelCloseReason,
+        /// The id of the ICA whose channel was closed.
+        #[serde(default)]
+        ica_id: u64,
     },
 }
 
@@ -90,8 +127,15 @@ impl IcaControllerCallbackMsg {
 }
 
 /// This is just a helper to properly serialize the above message.
-/// The actual receiver should include this variant in the larger ExecuteMsg enum
+/// The actual receiver should include this variant in the larger `ExecuteMsg` enum
 #[cw_serde]
 enum ReceiverExecuteMsg {
     ReceiveIcaCallback(IcaControllerCallbackMsg),
 }
+
+/// Returns whether the number is zero, so that the field is omitted for the receivers that do
+/// not know it.
+#[allow(clippy::trivially_copy_pass_by_ref)] // serde needs this signature
+const fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
@@ -2,7 +2,7 @@
 //! or a [`proto3json`](crate::ibc::types::metadata::TxEncoding::Proto3Json) string.
 
 use cosmos_sdk_proto::{prost::EncodeError, Any};
-use cosmwasm_std::CosmosMsg;
+use cosmwasm_std::{CosmosMsg, StdError, StdResult};
 
 /// `convert_to_proto_any` converts a [`CosmosMsg`] to a [`cosmos_sdk_proto::Any`].
 ///
@@ -56,6 +56,63 @@ pub fn convert_to_proto_any(msg: CosmosMsg, from_address: String) -> Result<Any,
     }
 }
 
+/// `convert_to_proto3json` converts a [`CosmosMsg`] to a json encoded [`cosmos_sdk_proto::Any`].
+///
+/// The [proto3json](https://protobuf.dev/programming-guides/proto3/#json) encoding is the one
+/// expected by the ICA host in a `CosmosTx` when the channel uses it.
+///
+/// `from_address` is the address of the interchain account.
+///
+/// # Errors
+///
+/// Returns an error if the [`CosmosMsg`] is not supported, or if the message of a
+/// [`CosmosMsg::Wasm`] is not valid json.
+///
+/// ## List of supported [`CosmosMsg`]
+///
+/// The same messages as [`convert_to_proto_any`], except [`CosmosMsg::Stargate`] since its
+/// protobuf value cannot be converted to json without its schema.
+pub fn convert_to_proto3json(msg: CosmosMsg, from_address: String) -> StdResult<String> {
+    let (json_msg, raw_msg) = match msg {
+        CosmosMsg::Bank(bank_msg) => (convert_to_json::bank(bank_msg, from_address)?, None),
+        CosmosMsg::Ibc(ibc_msg) => (convert_to_json::ibc(ibc_msg, from_address)?, None),
+        CosmosMsg::Wasm(wasm_msg) => convert_to_json::wasm(wasm_msg, from_address)?,
+        CosmosMsg::Gov(gov_msg) => (convert_to_json::gov(gov_msg, from_address), None),
+        #[cfg(feature = "staking")]
+        CosmosMsg::Staking(staking_msg) => {
+            (convert_to_json::staking(staking_msg, from_address)?, None)
+        }
+        #[cfg(feature = "staking")]
+        CosmosMsg::Distribution(distribution_msg) => (
+            convert_to_json::distribution(distribution_msg, from_address)?,
+            None,
+        ),
+        _ => {
+            return Err(StdError::generic_err(
+                "unsupported CosmosMsg for the proto3json encoding",
+            ))
+        }
+    };
+
+    let json =
+        serde_json_wasm::to_string(&json_msg).map_err(|e| StdError::generic_err(e.to_string()))?;
+
+    // The contract message is embedded as raw json rather than as a string.
+    match raw_msg {
+        Some(raw_msg) => {
+            serde_json_wasm::from_slice::<serde::de::IgnoredAny>(&raw_msg)
+                .map_err(|e| StdError::generic_err(format!("invalid contract msg: {e}")))?;
+            let raw_msg = String::from_utf8(raw_msg.to_vec())
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            Ok(format!(
+                r#"{},"msg":{raw_msg}}}"#,
+                json.strip_suffix('}').unwrap_or(&json)
+            ))
+        }
+        None => Ok(json),
+    }
+}
+
 mod convert_to_any {
     use cosmos_sdk_proto::{
         cosmos::{
@@ -330,11 +387,415 @@ mod convert_to_any {
     }
 }
 
+/// Contains the proto3json representations of the supported messages.
+/// Integers are encoded as strings, and enums by their names, as in the proto3json spec.
+mod convert_to_json {
+    use cosmwasm_std::{Binary, Coin, GovMsg, IbcMsg, StdError, StdResult, VoteOption, WasmMsg};
+    #[cfg(feature = "staking")]
+    use cosmwasm_std::{DistributionMsg, StakingMsg};
+    use serde::Serialize;
+
+    #[derive(Serialize)]
+    pub struct Height {
+        revision_number: String,
+        revision_height: String,
+    }
+
+    #[derive(Serialize)]
+    pub struct WeightedVoteOption {
+        option: &'static str,
+        weight: String,
+    }
+
+    /// The messages are tagged with their type URL, which is how `Any` is encoded in proto3json.
+    #[derive(Serialize)]
+    #[serde(tag = "@type")]
+    pub enum JsonMsg {
+        #[serde(rename = "/cosmos.bank.v1beta1.MsgSend")]
+        MsgSend {
+            from_address: String,
+            to_address: String,
+            amount: Vec<Coin>,
+        },
+        #[serde(rename = "/ibc.applications.transfer.v1.MsgTransfer")]
+        MsgTransfer {
+            source_port: String,
+            source_channel: String,
+            token: Coin,
+            sender: String,
+            receiver: String,
+            timeout_height: Height,
+            timeout_timestamp: String,
+            memo: String,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgExecuteContract")]
+        MsgExecuteContract {
+            sender: String,
+            contract: String,
+            funds: Vec<Coin>,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgInstantiateContract")]
+        MsgInstantiateContract {
+            sender: String,
+            admin: String,
+            code_id: String,
+            label: String,
+            funds: Vec<Coin>,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgInstantiateContract2")]
+        MsgInstantiateContract2 {
+            sender: String,
+            admin: String,
+            code_id: String,
+            label: String,
+            funds: Vec<Coin>,
+            salt: Binary,
+            fix_msg: bool,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgMigrateContract")]
+        MsgMigrateContract {
+            sender: String,
+            contract: String,
+            code_id: String,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgUpdateAdmin")]
+        MsgUpdateAdmin {
+            sender: String,
+            new_admin: String,
+            contract: String,
+        },
+        #[serde(rename = "/cosmwasm.wasm.v1.MsgClearAdmin")]
+        MsgClearAdmin { sender: String, contract: String },
+        #[serde(rename = "/cosmos.gov.v1beta1.MsgVote")]
+        MsgVote {
+            proposal_id: String,
+            voter: String,
+            option: &'static str,
+        },
+        #[serde(rename = "/cosmos.gov.v1.MsgVoteWeighted")]
+        MsgVoteWeighted {
+            proposal_id: String,
+            voter: String,
+            options: Vec<WeightedVoteOption>,
+            metadata: String,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.staking.v1beta1.MsgDelegate")]
+        MsgDelegate {
+            delegator_address: String,
+            validator_address: String,
+            amount: Coin,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.staking.v1beta1.MsgUndelegate")]
+        MsgUndelegate {
+            delegator_address: String,
+            validator_address: String,
+            amount: Coin,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.staking.v1beta1.MsgBeginRedelegate")]
+        MsgBeginRedelegate {
+            delegator_address: String,
+            validator_src_address: String,
+            validator_dst_address: String,
+            amount: Coin,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward")]
+        MsgWithdrawDelegatorReward {
+            delegator_address: String,
+            validator_address: String,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.distribution.v1beta1.MsgSetWithdrawAddress")]
+        MsgSetWithdrawAddress {
+            delegator_address: String,
+            withdraw_address: String,
+        },
+        #[cfg(feature = "staking")]
+        #[serde(rename = "/cosmos.distribution.v1beta1.MsgFundCommunityPool")]
+        MsgFundCommunityPool {
+            amount: Vec<Coin>,
+            depositor: String,
+        },
+    }
+
+    fn unsupported(msg_type: &str) -> StdError {
+        StdError::generic_err(format!(
+            "unsupported {msg_type} for the proto3json encoding"
+        ))
+    }
+
+    pub fn bank(msg: cosmwasm_std::BankMsg, from_address: String) -> StdResult<JsonMsg> {
+        match msg {
+            cosmwasm_std::BankMsg::Send { to_address, amount } => Ok(JsonMsg::MsgSend {
+                from_address,
+                to_address,
+                amount,
+            }),
+            _ => Err(unsupported("BankMsg")),
+        }
+    }
+
+    pub fn ibc(msg: IbcMsg, sender: String) -> StdResult<JsonMsg> {
+        match msg {
+            IbcMsg::Transfer {
+                channel_id,
+                to_address,
+                amount,
+                timeout,
+                memo,
+            } => Ok(JsonMsg::MsgTransfer {
+                source_port: "transfer".to_string(),
+                source_channel: channel_id,
+                token: amount,
+                sender,
+                receiver: to_address,
+                timeout_height: Height {
+                    revision_number: timeout
+                        .block()
+                        .map_or(0, |block| block.revision)
+                        .to_string(),
+                    revision_height: timeout.block().map_or(0, |block| block.height).to_string(),
+                },
+                timeout_timestamp: timeout
+                    .timestamp()
+                    .map_or(0, |timestamp| timestamp.nanos())
+                    .to_string(),
+                memo: memo.unwrap_or_default(),
+            }),
+            _ => Err(unsupported("IbcMsg")),
+        }
+    }
+
+    /// Returns the message without the contract message, which must be embedded as raw json.
+    pub fn wasm(msg: WasmMsg, sender: String) -> StdResult<(JsonMsg, Option<Binary>)> {
+        match msg {
+            WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            } => Ok((
+                JsonMsg::MsgExecuteContract {
+                    sender,
+                    contract: contract_addr,
+                    funds,
+                },
+                Some(msg),
+            )),
+            WasmMsg::Instantiate {
+                admin,
+                code_id,
+                msg,
+                funds,
+                label,
+            } => Ok((
+                JsonMsg::MsgInstantiateContract {
+                    sender,
+                    admin: admin.unwrap_or_default(),
+                    code_id: code_id.to_string(),
+                    label,
+                    funds,
+                },
+                Some(msg),
+            )),
+            WasmMsg::Instantiate2 {
+                admin,
+                code_id,
+                label,
+                msg,
+                funds,
+                salt,
+            } => Ok((
+                JsonMsg::MsgInstantiateContract2 {
+                    sender,
+                    admin: admin.unwrap_or_default(),
+                    code_id: code_id.to_string(),
+                    label,
+                    funds,
+                    salt,
+                    fix_msg: false,
+                },
+                Some(msg),
+            )),
+            WasmMsg::Migrate {
+                contract_addr,
+                new_code_id,
+                msg,
+            } => Ok((
+                JsonMsg::MsgMigrateContract {
+                    sender,
+                    contract: contract_addr,
+                    code_id: new_code_id.to_string(),
+                },
+                Some(msg),
+            )),
+            WasmMsg::UpdateAdmin {
+                contract_addr,
+                admin,
+            } => Ok((
+                JsonMsg::MsgUpdateAdmin {
+                    sender,
+                    new_admin: admin,
+                    contract: contract_addr,
+                },
+                None,
+            )),
+            WasmMsg::ClearAdmin { contract_addr } => Ok((
+                JsonMsg::MsgClearAdmin {
+                    sender,
+                    contract: contract_addr,
+                },
+                None,
+            )),
+            _ => Err(unsupported("WasmMsg")),
+        }
+    }
+
+    pub fn gov(msg: GovMsg, voter: String) -> JsonMsg {
+        const fn vote_option_name(option: &VoteOption) -> &'static str {
+            match option {
+                VoteOption::Yes => "VOTE_OPTION_YES",
+                VoteOption::No => "VOTE_OPTION_NO",
+                VoteOption::Abstain => "VOTE_OPTION_ABSTAIN",
+                VoteOption::NoWithVeto => "VOTE_OPTION_NO_WITH_VETO",
+            }
+        }
+
+        match msg {
+            GovMsg::Vote {
+                proposal_id,
+                option,
+            } => JsonMsg::MsgVote {
+                proposal_id: proposal_id.to_string(),
+                voter,
+                option: vote_option_name(&option),
+            },
+            GovMsg::VoteWeighted {
+                proposal_id,
+                options,
+            } => JsonMsg::MsgVoteWeighted {
+                proposal_id: proposal_id.to_string(),
+                voter,
+                options: options
+                    .into_iter()
+                    .map(|weighted_option| WeightedVoteOption {
+                        option: vote_option_name(&weighted_option.option),
+                        weight: weighted_option.weight.to_string(),
+                    })
+                    .collect(),
+                metadata: String::new(),
+            },
+        }
+    }
+
+    #[cfg(feature = "staking")]
+    pub fn staking(msg: StakingMsg, delegator_address: String) -> StdResult<JsonMsg> {
+        match msg {
+            StakingMsg::Delegate { validator, amount } => Ok(JsonMsg::MsgDelegate {
+                delegator_address,
+                validator_address: validator,
+                amount,
+            }),
+            StakingMsg::Undelegate { validator, amount } => Ok(JsonMsg::MsgUndelegate {
+                delegator_address,
+                validator_address: validator,
+                amount,
+            }),
+            StakingMsg::Redelegate {
+                src_validator,
+                dst_validator,
+                amount,
+            } => Ok(JsonMsg::MsgBeginRedelegate {
+                delegator_address,
+                validator_src_address: src_validator,
+                validator_dst_address: dst_validator,
+                amount,
+            }),
+            _ => Err(unsupported("StakingMsg")),
+        }
+    }
+
+    #[cfg(feature = "staking")]
+    pub fn distribution(msg: DistributionMsg, sender: String) -> StdResult<JsonMsg> {
+        match msg {
+            DistributionMsg::WithdrawDelegatorReward { validator } => {
+                Ok(JsonMsg::MsgWithdrawDelegatorReward {
+                    delegator_address: sender,
+                    validator_address: validator,
+                })
+            }
+            DistributionMsg::SetWithdrawAddress { address } => Ok(JsonMsg::MsgSetWithdrawAddress {
+                delegator_address: sender,
+                withdraw_address: address,
+            }),
+            DistributionMsg::FundCommunityPool { amount } => Ok(JsonMsg::MsgFundCommunityPool {
+                amount,
+                depositor: sender,
+            }),
+            _ => Err(unsupported("DistributionMsg")),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
-    use cosmwasm_std::{Decimal, Uint128, VoteOption, WeightedVoteOption};
+    use cosmwasm_std::{
+        coins, BankMsg, CosmosMsg, Decimal, GovMsg, Uint128, VoteOption, WasmMsg,
+        WeightedVoteOption,
+    };
+
+    use super::convert_to_proto3json;
+
+    #[test]
+    fn test_convert_to_proto3json() {
+        let bank_msg = CosmosMsg::Bank(BankMsg::Send {
+            to_address: "receiver".to_string(),
+            amount: coins(100, "stake"),
+        });
+        assert_eq!(
+            convert_to_proto3json(bank_msg, "ica".to_string()).unwrap(),
+            r#"{"@type":"/cosmos.bank.v1beta1.MsgSend","from_address":"ica","to_address":"receiver","amount":[{"denom":"stake","amount":"100"}]}"#
+        );
+
+        let gov_msg = CosmosMsg::Gov(GovMsg::Vote {
+            proposal_id: 1,
+            option: VoteOption::NoWithVeto,
+        });
+        assert_eq!(
+            convert_to_proto3json(gov_msg, "ica".to_string()).unwrap(),
+            r#"{"@type":"/cosmos.gov.v1beta1.MsgVote","proposal_id":"1","voter":"ica","option":"VOTE_OPTION_NO_WITH_VETO"}"#
+        );
+
+        // The contract message is embedded as json
+        let wasm_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "contract".to_string(),
+            msg: br#"{"increment":{}}"#.into(),
+            funds: vec![],
+        });
+        assert_eq!(
+            convert_to_proto3json(wasm_msg, "ica".to_string()).unwrap(),
+            r#"{"@type":"/cosmwasm.wasm.v1.MsgExecuteContract","sender":"ica","contract":"contract","funds":[],"msg":{"increment":{}}}"#
+        );
+
+        let invalid_wasm_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "contract".to_string(),
+            msg: b"not json".into(),
+            funds: vec![],
+        });
+        assert!(convert_to_proto3json(invalid_wasm_msg, "ica".to_string()).is_err());
+
+        // Stargate messages cannot be converted without their schema
+        #[allow(deprecated)]
+        let stargate_msg = CosmosMsg::Stargate {
+            type_url: "/cosmos.bank.v1beta1.MsgSend".to_string(),
+            value: vec![].into(),
+        };
+        assert!(convert_to_proto3json(stargate_msg, "ica".to_string()).is_err());
+    }
 
     #[test]
     fn test_weighted_vote_option() {
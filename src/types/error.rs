@@ -62,12 +62,30 @@ pub enum ContractError {
     #[error("MsgChannelCloseInit is not allowed")]
     ChannelCloseInitNotAllowed,
 
+    #[error("MsgChannelOpenTry is not allowed: the ICA controller cannot accept a channel from {counterparty_port_id} on {connection_id}")]
+    ChannelOpenTryNotAllowed {
+        counterparty_port_id: String,
+        connection_id: String,
+    },
+
+    #[error("MsgChannelOpenConfirm is not allowed: the ICA controller does not accept channels, got {channel_id}")]
+    ChannelOpenConfirmNotAllowed { channel_id: String },
+
+    #[error("the ICA controller cannot receive packets")]
+    CannotReceivePackets,
+
     #[error("codec is not supported: unsupported codec format {0}")]
     UnsupportedCodec(String),
 
     #[error("invalid interchain account address")]
     InvalidIcaAddress,
 
+    #[error("invalid interchain account address: {0}")]
+    InvalidIcaAddressBech32(#[from] bech32::primitives::decode::CheckedHrpstringError),
+
+    #[error("invalid interchain account address prefix: expected {expected}, got {actual}")]
+    InvalidIcaAddressPrefix { expected: String, actual: String },
+
     #[error("unsupported transaction type {0}")]
     UnsupportedTxType(String),
 
@@ -98,9 +116,75 @@ pub enum ContractError {
     #[error("unsupported packet encoding: {0}")]
     UnsupportedPacketEncoding(String),
 
+    #[error("unsupported query: {0}")]
+    UnsupportedQuery(String),
+
+    #[error("insufficient contract balance to pay the bounty of {0}")]
+    InsufficientBountyBalance(String),
+
     #[error("empty response: {0}")]
     EmptyResponse(String),
 
     #[error("unknown reply id: {0}")]
     UnknownReplyId(u64),
+
+    #[error("failed to send the packet: {0}")]
+    SendPacketFailed(String),
+
+    #[error("failed to register the interchain account: {0}")]
+    RegisterInterchainAccountFailed(String),
+
+    #[error("no sequence found in the send packet response")]
+    SendPacketSequenceNotFound,
+
+    #[error("message type {0} is not allowed by the interchain accounts host")]
+    MessageNotAllowedByHost(String),
+
+    #[error("invalid query subscription: {0}")]
+    InvalidQuerySubscription(String),
+
+    #[error("query subscription {0} not found")]
+    QuerySubscriptionNotFound(u64),
+
+    #[error("no query subscriptions are due")]
+    NoDueQuerySubscriptions,
+
+    #[error("connection {connection_id} is already used by ica {ica_id}")]
+    ConnectionUsedByOtherIca { connection_id: String, ica_id: u64 },
+
+    #[error("interchain account address changed: expected {expected}, got {actual}")]
+    IcaAddressChanged { expected: String, actual: String },
+
+    #[error("host chain id mismatch: expected {expected}, got {actual}")]
+    HostChainIdMismatch { expected: String, actual: String },
+
+    #[error("unsupported client state: {0}")]
+    UnsupportedClientState(String),
+
+    #[error("invalid auto reopen policy: max_attempts must be greater than zero")]
+    InvalidAutoReopenPolicy,
+
+    #[error("{0} is not supported by the controller module backend")]
+    UnsupportedByControllerModule(String),
+
+    #[error("ibc source callbacks are only accepted with the controller module backend")]
+    UnexpectedSourceCallback,
+
+    #[error("a queue entry must have at least one message or query")]
+    EmptyQueueEntry,
+
+    #[error("the queue of ica {0} is empty")]
+    QueueEmpty(u64),
+
+    #[error("no queue entry of the sender with correlation id {0}")]
+    QueueEntryNotFound(String),
+
+    #[error("queued packet is too large: {size} bytes, the maximum is {max} bytes")]
+    QueuedPacketTooLarge { size: usize, max: usize },
+
+    #[error("invalid packet timeout: {0}")]
+    InvalidPacketTimeout(String),
+
+    #[error("invalid packet memo: {0}")]
+    InvalidPacketMemo(String),
 }
@@ -10,8 +10,23 @@ pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// This module contains [`cosmwasm_std::SubMsg`] reply ids.
 pub mod reply_ids {
-    /// `SEND_QUERY_PACKET` is the reply id for a packet sent using
-    /// [`crate::types::msg::ExecuteMsg::SendQueryMsgs`]
-    #[cfg(feature = "query")]
-    pub const SEND_QUERY_PACKET: u64 = 1;
+    /// `SEND_PACKET` is the reply id for the ICA packets sent by the contract.
+    /// The reply records the sequence of the packet in the pending packets registry.
+    pub const SEND_PACKET: u64 = 1;
+    /// `SEND_QUERY_PACKET` is the former name of [`SEND_PACKET`], from when only the packets
+    /// with queries were replied to.
+    #[deprecated(note = "use `SEND_PACKET` instead")]
+    pub const SEND_QUERY_PACKET: u64 = SEND_PACKET;
+    /// `REGISTER_INTERCHAIN_ACCOUNT` is the reply id for a `MsgRegisterInterchainAccount` sent
+    /// with the [`IcaBackend::ControllerModule`](crate::types::msg::options::IcaBackend) backend.
+    pub const REGISTER_INTERCHAIN_ACCOUNT: u64 = 2;
+    /// `CHANNEL_CLOSE_CALLBACK` is the reply id for the `OnChannelCloseCallback` message.
+    /// The reply ignores the errors of the callback receiver, so that they do not revert the
+    /// channel close.
+    pub const CHANNEL_CLOSE_CALLBACK: u64 = 3;
+    /// `RESEND_PACKET` is the reply id for the packets that are resent by their retry policy.
+    ///
+    /// The reply records the sequence like [`SEND_PACKET`], and a failed resend is replied to
+    /// with the timeout callback of the packet, so that it does not revert the timeout.
+    pub const RESEND_PACKET: u64 = 4;
 }
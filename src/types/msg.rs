@@ -18,6 +18,15 @@ pub struct InstantiateMsg {
     /// If not specified, then no callbacks are sent.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub send_callbacks_to: Option<String>,
+    /// The backend used to open and drive the interchain accounts.
+    /// If not specified, [`options::IcaBackend::WasmPort`] is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<options::IcaBackend>,
+    /// The counterparty ports, other than `icahost`, that are accepted as ICA hosts.
+    /// Must include the `counterparty_port_id` of the channel opened on instantiation if it is a
+    /// custom ICA host. See [`ExecuteMsg::UpdateAllowedHostPorts`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_host_ports: Option<Vec<String>>,
 }
 
 /// The messages to execute the ICA controller contract.
@@ -26,24 +35,47 @@ pub struct InstantiateMsg {
 #[non_exhaustive]
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// `CreateChannel` makes the contract submit a stargate MsgChannelOpenInit to the chain.
+    /// `CreateChannel` makes the contract submit a stargate `MsgChannelOpenInit` to the chain.
     /// This is a wrapper around [`options::ChannelOpenInitOptions`] and thus requires the
     /// same fields. If not specified, then the options specified in the contract instantiation
     /// are used.
+    ///
+    /// A contract can only have one ICA per connection, so two ICAs on the same host chain need
+    /// two connections. Port suffixes are not supported, since wasmd binds a single IBC port to
+    /// each contract.
     CreateChannel {
         /// The options to initialize the IBC channel.
         /// If not specified, the options specified in the last channel creation are used.
         /// Must be `None` if the sender is not the owner.
         #[serde(skip_serializing_if = "Option::is_none")]
         channel_open_init_options: Option<options::ChannelOpenInitOptions>,
+        /// The id of the ICA to open the channel for. Defaults to `0`, which is the ICA
+        /// whose channel is opened on instantiation.
+        ///
+        /// A new `ica_id` requires `channel_open_init_options` with a connection that is not used
+        /// by any other ICA of this contract.
+        #[serde(default)]
+        ica_id: u64,
+        /// Whether the channel handshake may complete with an ICA address that differs from the
+        /// address previously known for this `ica_id`. Defaults to `false`, in which case a reopened
+        /// channel that maps to a different interchain account is rejected.
+        #[serde(default)]
+        allow_new_ica_address: bool,
     },
     /// `CloseChannel` closes the IBC channel.
-    CloseChannel {},
+    CloseChannel {
+        /// The id of the ICA whose channel is closed. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
     /// `SendCosmosMsgs` converts the provided array of [`CosmosMsg`] to an ICA tx and sends them to the ICA host.
-    /// [`CosmosMsg::Stargate`] and [`CosmosMsg::Wasm`] are only supported if the [`TxEncoding`](crate::ibc::types::metadata::TxEncoding) is [`TxEncoding::Protobuf`](crate::ibc::types::metadata::TxEncoding).
+    /// [`CosmosMsg::Stargate`] is only supported if the negotiated [`TxEncoding`](crate::ibc::types::metadata::TxEncoding) is [`TxEncoding::Protobuf`](crate::ibc::types::metadata::TxEncoding).
     ///
     /// **This is the recommended way to send messages to the ICA host.**
     SendCosmosMsgs {
+        /// The id of the ICA that sends the messages. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
         /// The stargate messages to convert and send to the ICA host.
         #[serde_as(deserialize_as = "serde_with::DefaultOnNull")]
         messages: Vec<CosmosMsg>,
@@ -58,9 +90,104 @@ pub enum ExecuteMsg {
         #[serde(skip_serializing_if = "Option::is_none")]
         packet_memo: Option<String>,
         /// Optional timeout in seconds to include with the ibc packet.
-        /// If not specified, the [default timeout](crate::ibc::types::packet::DEFAULT_TIMEOUT_SECONDS) is used.
+        /// If neither this nor `timeout` is specified, the default timeout set with
+        /// [`ExecuteMsg::UpdateDefaultTimeout`] is used.
         #[serde(skip_serializing_if = "Option::is_none")]
         timeout_seconds: Option<u64>,
+        /// Optional absolute timestamp and counterparty block height after which the ibc packet
+        /// times out. Cannot be used together with `timeout_seconds`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout: Option<options::PacketTimeout>,
+        /// Optional policy to resend the packet if it times out.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry: Option<options::RetryPolicy>,
+    },
+    /// `Enqueue` adds messages and queries to the queue of an ICA, to be sent in a single packet
+    /// with the entries of other senders by [`ExecuteMsg::Flush`].
+    ///
+    /// Can be called by the owner and the addresses set with [`ExecuteMsg::UpdateEnqueuers`].
+    /// The entry is rejected if the sum of the packet sizes of the queued entries would exceed
+    /// [`MAX_QUEUED_PACKET_SIZE`].
+    Enqueue {
+        /// The id of the ICA whose queue the messages are added to. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The messages to send to the ICA host.
+        #[serde_as(deserialize_as = "serde_with::DefaultOnNull")]
+        messages: Vec<CosmosMsg>,
+        /// The queries to send to the ICA host.
+        /// The queries of all the entries are executed after the messages of all the entries.
+        #[cfg(feature = "query")]
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        #[serde(default)]
+        #[serde_as(deserialize_as = "serde_with::DefaultOnNull")]
+        queries: Vec<cosmwasm_std::QueryRequest<cosmwasm_std::Empty>>,
+        /// An id chosen by the sender, which is returned in the packet callbacks together with
+        /// the indices of the messages and queries of this entry.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        correlation_id: Option<String>,
+    },
+    /// `Flush` sends the queue of an ICA to the ICA host in a single packet, with the entries in
+    /// the order they were added, and empties the queue.
+    ///
+    /// Can be called by the owner and the addresses set with [`ExecuteMsg::UpdateEnqueuers`].
+    Flush {
+        /// The id of the ICA whose queue is sent. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// Optional memo to include in the ibc packet.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        packet_memo: Option<String>,
+        /// Optional timeout in seconds to include with the ibc packet.
+        /// If neither this nor `timeout` is specified, the default timeout set with
+        /// [`ExecuteMsg::UpdateDefaultTimeout`] is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout_seconds: Option<u64>,
+        /// Optional absolute timestamp and counterparty block height after which the ibc packet
+        /// times out. Cannot be used together with `timeout_seconds`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout: Option<options::PacketTimeout>,
+        /// Optional policy to resend the packet if it times out.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry: Option<options::RetryPolicy>,
+    },
+    /// `Dequeue` removes the entries of the sender with the given correlation id from the queue
+    /// of an ICA, for example when the host no longer allows their messages and the queue cannot
+    /// be flushed. Can be called by anyone, for their own entries.
+    Dequeue {
+        /// The id of the ICA whose queue the entries are removed from. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The correlation id of the entries to remove.
+        correlation_id: String,
+    },
+    /// `ClearQueue` removes all the entries from the queue of an ICA without sending them.
+    ClearQueue {
+        /// The id of the ICA whose queue is cleared. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `UpdateEnqueuers` replaces the addresses that are allowed to call [`ExecuteMsg::Enqueue`]
+    /// and [`ExecuteMsg::Flush`], other than the owner.
+    UpdateEnqueuers {
+        /// The allowed addresses.
+        enqueuers: Vec<String>,
+    },
+    /// `UpdateDefaultTimeout` sets the timeout in seconds of the packets that are sent without a
+    /// timeout.
+    UpdateDefaultTimeout {
+        /// The new default timeout, which must be greater than zero. If not specified, then
+        /// [`DEFAULT_TIMEOUT_SECONDS`](crate::ibc::types::packet::DEFAULT_TIMEOUT_SECONDS) is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        timeout_seconds: Option<u64>,
+    },
+    /// `UpdatePacketHistoryRetention` sets the maximum number of outcomes kept in the packet
+    /// history. The oldest outcomes are removed first.
+    UpdatePacketHistoryRetention {
+        /// The new retention limit. Zero disables the packet history. If not specified, then
+        /// [`DEFAULT_PACKET_HISTORY_RETENTION`] is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retention: Option<u64>,
     },
     /// `UpdateCallbackAddress` updates the contract callback address.
     UpdateCallbackAddress {
@@ -68,29 +195,233 @@ pub enum ExecuteMsg {
         /// If not specified, then no callbacks are sent.
         callback_address: Option<String>,
     },
+    /// `UpdateAutoReopenPolicy` sets or removes the policy to automatically reopen the channel
+    /// of an ICA after an ordered channel is closed by a packet timeout.
+    UpdateAutoReopenPolicy {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The new policy. If not specified, then the channel is not reopened automatically.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        policy: Option<options::AutoReopenPolicy>,
+    },
+    /// `UpdateAllowedHostPorts` replaces the counterparty ports that are allowed as ICA hosts.
+    /// [`HOST_PORT_ID`](crate::ibc::types::keys::HOST_PORT_ID) is always allowed.
+    UpdateAllowedHostPorts {
+        /// The allowed host ports, such as the `wasm.*` port of a wasm-based ICA host.
+        ports: Vec<String>,
+    },
+    /// `RefreshHostAllowMessages` queries the `allow_messages` parameter of the ICA host through
+    /// the interchain account, and caches it once the acknowledgement is received.
+    ///
+    /// Once cached, [`ExecuteMsg::SendCosmosMsgs`] rejects the messages that the host does not allow.
+    RefreshHostAllowMessages {
+        /// The id of the ICA whose host is queried. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `SyncChannel` queries the status of the channel of an ICA from this chain, and updates the
+    /// channel status in the contract if it changed. This message can be sent by anyone.
+    SyncChannel {
+        /// The id of the ICA whose channel is synced. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `RegisterQuerySubscription` registers a set of queries that anyone can send to the ICA host
+    /// with [`ExecuteMsg::Crank`] once every `interval_seconds`.
+    ///
+    /// The subscription is due immediately after it is registered.
+    #[cfg(feature = "query")]
+    RegisterQuerySubscription {
+        /// The id of the ICA that sends the queries. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The queries to send to the ICA host.
+        queries: Vec<cosmwasm_std::QueryRequest<cosmwasm_std::Empty>>,
+        /// The minimum number of seconds between two query packets. Must be greater than zero.
+        interval_seconds: u64,
+        /// Whether the latest result is cached in the contract, see [`QueryMsg::GetQuerySubscriptionResult`].
+        /// Callbacks are sent to the callback address regardless.
+        #[serde(default)]
+        cache_results: bool,
+        /// The bounty paid by this contract to the sender of each crank of this subscription.
+        /// The contract must hold enough funds to pay it, otherwise the crank fails.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bounty: Option<cosmwasm_std::Coin>,
+    },
+    /// `CancelQuerySubscription` removes a query subscription.
+    #[cfg(feature = "query")]
+    CancelQuerySubscription {
+        /// The id of the subscription to remove.
+        subscription_id: u64,
+    },
+    /// `Crank` sends a query packet for each due query subscription, in the order of their ids.
+    /// This message can be sent by anyone, and the sender receives the bounties of the cranked
+    /// subscriptions.
+    #[cfg(feature = "query")]
+    Crank {
+        /// The maximum number of subscriptions to crank.
+        /// If not specified, [`DEFAULT_CRANK_LIMIT`] is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
+    },
 }
 
+/// The default maximum number of subscriptions cranked by [`ExecuteMsg::Crank`].
+#[cfg(feature = "query")]
+pub const DEFAULT_CRANK_LIMIT: u32 = 10;
+
+/// The default maximum number of outcomes kept in the packet history.
+pub const DEFAULT_PACKET_HISTORY_RETENTION: u64 = 1000;
+
+/// The maximum size in bytes of the packet data built from the queue of an ICA.
+pub const MAX_QUEUED_PACKET_SIZE: usize = 64 * 1024;
+
 /// The messages to query the ICA controller contract.
 #[cw_ownable::cw_ownable_query]
 #[non_exhaustive]
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
-    /// GetChannel returns the IBC channel info.
+    /// `GetChannel` returns the IBC channel info of an ICA.
     #[returns(crate::types::state::ChannelState)]
-    GetChannel {},
-    /// GetContractState returns the contact's state.
+    GetChannel {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `GetIcaInfo` returns the address, channel and encoding of an ICA.
+    #[returns(crate::types::state::IcaInfo)]
+    GetIcaInfo {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `GetContractState` returns the contact's state.
     #[returns(crate::types::state::ContractState)]
     GetContractState {},
+    /// `GetAllowedHostPorts` returns the counterparty ports that are allowed as ICA hosts,
+    /// other than [`HOST_PORT_ID`](crate::ibc::types::keys::HOST_PORT_ID).
+    #[returns(Vec<String>)]
+    GetAllowedHostPorts {},
+    /// `GetAutoReopenPolicy` returns the auto reopen policy of an ICA, if any.
+    #[returns(Option<options::AutoReopenPolicy>)]
+    GetAutoReopenPolicy {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `GetDefaultTimeout` returns the timeout in seconds of the packets that are sent without a timeout.
+    #[returns(u64)]
+    GetDefaultTimeout {},
+    /// `GetEnqueuers` returns the addresses that are allowed to enqueue messages, other than the owner.
+    #[returns(Vec<cosmwasm_std::Addr>)]
+    GetEnqueuers {},
+    /// `GetQueue` returns the queued entries of an ICA, in the order they were added.
+    #[returns(Vec<crate::types::state::queue::QueueEntry>)]
+    GetQueue {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `GetHostAllowMessages` returns the cached `allow_messages` parameter of the ICA host.
+    /// Returns `None` if it has not been fetched yet.
+    #[returns(Option<Vec<String>>)]
+    GetHostAllowMessages {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+    },
+    /// `GetChannelHistory` returns the channels of an ICA in the order they were opened,
+    /// together with their index in the history.
+    #[returns(Vec<(u64, crate::types::state::ChannelRecord)>)]
+    GetChannelHistory {
+        /// The id of the ICA. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The index after which the records are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_after: Option<u64>,
+        /// The maximum number of records to return.
+        /// If not specified, [`DEFAULT_PAGE_LIMIT`] is used. At most [`MAX_PAGE_LIMIT`] records are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
+    },
+    /// `GetPendingPackets` returns the packets sent by the contract that have not been acknowledged
+    /// or timed out yet, ordered by channel and sequence.
+    #[returns(Vec<crate::types::state::packet::PendingPacket>)]
+    GetPendingPackets {
+        /// The channel and sequence after which the packets are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_after: Option<(String, u64)>,
+        /// The maximum number of packets to return.
+        /// If not specified, [`DEFAULT_PAGE_LIMIT`] is used. At most [`MAX_PAGE_LIMIT`] packets are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
+    },
+    /// `GetPacket` returns the pending packet with the given sequence.
+    /// Returns `None` if the packet has been acknowledged or timed out, or was never sent.
+    #[returns(Option<crate::types::state::packet::PendingPacket>)]
+    GetPacket {
+        /// The id of the ICA that sent the packet. Defaults to `0`.
+        #[serde(default)]
+        ica_id: u64,
+        /// The sequence of the packet.
+        sequence: u64,
+        /// The source channel of the packet.
+        /// If not specified, the current channel of the ICA is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        channel_id: Option<String>,
+    },
+    /// `GetPacketHistory` returns the outcomes of the acknowledged and timed out packets in the
+    /// order they were received, together with their index in the history.
+    #[returns(Vec<(u64, crate::types::state::packet::PacketOutcome)>)]
+    GetPacketHistory {
+        /// The index after which the outcomes are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_after: Option<u64>,
+        /// The maximum number of outcomes to return.
+        /// If not specified, [`DEFAULT_PAGE_LIMIT`] is used. At most [`MAX_PAGE_LIMIT`] outcomes are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
+        /// If specified, only the outcomes with this status are returned.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        status: Option<crate::types::state::packet::PacketStatus>,
+    },
+    /// `GetPacketHistoryRetention` returns the maximum number of outcomes kept in the packet history.
+    #[returns(u64)]
+    GetPacketHistoryRetention {},
+    /// `GetQuerySubscription` returns the query subscription with the given id.
+    #[cfg(feature = "query")]
+    #[returns(crate::types::state::ica_query::Subscription)]
+    GetQuerySubscription {
+        /// The id of the subscription.
+        subscription_id: u64,
+    },
+    /// `GetQuerySubscriptionResult` returns the latest cached result of the query subscription.
+    /// Returns `None` if no result has been cached yet.
+    #[cfg(feature = "query")]
+    #[returns(Option<crate::types::state::ica_query::SubscriptionResult>)]
+    GetQuerySubscriptionResult {
+        /// The id of the subscription.
+        subscription_id: u64,
+    },
 }
 
+/// The default number of items returned by the paginated queries.
+pub const DEFAULT_PAGE_LIMIT: u32 = 10;
+/// The maximum number of items returned by the paginated queries.
+pub const MAX_PAGE_LIMIT: u32 = 30;
+
 /// The message to migrate this contract.
 #[cw_serde]
 pub struct MigrateMsg {}
 
 /// Option types for other messages.
 pub mod options {
-    use cosmwasm_std::IbcOrder;
+    use cosmwasm_std::{IbcOrder, IbcTimeoutBlock, Timestamp};
+
+    use crate::ibc::types::metadata::TxEncoding;
 
     /// The options needed to initialize the IBC channel.
     #[derive(
@@ -109,12 +440,87 @@ pub mod options {
         /// The counterparty connection id on the counterparty chain.
         pub counterparty_connection_id: String,
         /// The counterparty port id. If not specified, [`crate::ibc::types::keys::HOST_PORT_ID`] is used.
-        /// Currently, this contract only supports the host port.
+        /// Other ports must be allowed with [`super::ExecuteMsg::UpdateAllowedHostPorts`].
         pub counterparty_port_id: Option<String>,
         /// The order of the channel. If not specified, [`IbcOrder::Ordered`] is used.
         /// [`IbcOrder::Unordered`] is only supported if the counterparty chain is using `ibc-go`
         /// v8.1.0 or later.
         pub channel_ordering: Option<IbcOrder>,
+        /// The chain id that the connection must point to. If specified, the contract checks it
+        /// against the client state of the connection before opening the channel, and stores it
+        /// in the ICA info. This requires the chain to allow the
+        /// [`ConnectionClientState`](crate::ibc::types::stargate::connection::CONNECTION_CLIENT_STATE_PATH)
+        /// gRPC query from contracts.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub expected_host_chain_id: Option<String>,
+        /// The bech32 prefix that the ICA address must have, such as `cosmos`.
+        /// If not specified, any prefix is accepted.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub expected_address_prefix: Option<String>,
+        /// The tx encodings that the contract accepts, in order of preference. The first one is
+        /// proposed to the ICA host, and a counter-proposal of the host is accepted if it is in
+        /// this list. If empty, only [`TxEncoding::Protobuf`] is accepted.
+        ///
+        /// The tx type is not negotiated: `sdk_multi_msg` is the only tx type that ibc-go defines,
+        /// and the handshake is rejected if the host proposes another one.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub tx_encodings: Vec<TxEncoding>,
+    }
+
+    /// The backend that the contract uses to open the ICA channels and send the packets.
+    /// It is chosen on instantiation and cannot be changed afterwards.
+    #[cosmwasm_schema::cw_serde]
+    #[derive(Default)]
+    pub enum IcaBackend {
+        /// The contract owns its `wasm.<contract_address>` port and implements the ICS-27
+        /// controller in its IBC entry points.
+        #[default]
+        WasmPort,
+        /// The contract drives the `ibc-go` interchain accounts controller module with
+        /// `MsgRegisterInterchainAccount` and `MsgSendTx`, and receives the packet lifecycle
+        /// events in its `ibc_source_callback` entry point. The channel is owned by the
+        /// `icacontroller-<contract_address>` port.
+        ControllerModule,
+    }
+
+    /// The absolute timeout of an ICA packet. The packet times out when either of the set
+    /// fields is reached on the counterparty chain, so at least one of them must be set.
+    #[cosmwasm_schema::cw_serde]
+    pub struct PacketTimeout {
+        /// The block time of the counterparty chain after which the packet times out.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub timestamp: Option<Timestamp>,
+        /// The block height of the counterparty chain after which the packet times out.
+        /// Not supported by the [`IcaBackend::ControllerModule`] backend.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub block: Option<IbcTimeoutBlock>,
+    }
+
+    /// The policy to automatically reopen the channel of an ICA after an ordered channel is
+    /// closed by a packet timeout. The channel is reopened with the saved [`ChannelOpenInitOptions`].
+    #[cosmwasm_schema::cw_serde]
+    pub struct AutoReopenPolicy {
+        /// The maximum number of consecutive reopen attempts. The count is reset once a channel
+        /// open handshake completes, so that a channel that cannot be opened is not retried forever.
+        pub max_attempts: u32,
+    }
+
+    /// The policy to automatically resend a packet after it times out.
+    ///
+    /// On unordered channels the packet is resent right away. On ordered channels, the timeout
+    /// closes the channel, so the packet is resent once the channel is reopened by the
+    /// [`AutoReopenPolicy`] of the ICA. The packet is not resent if the channel is not reopened.
+    ///
+    /// The retries always use a relative timestamp timeout, so a packet with an absolute
+    /// [`PacketTimeout`] cannot have a retry policy.
+    #[cosmwasm_schema::cw_serde]
+    pub struct RetryPolicy {
+        /// The maximum number of times the packet is resent.
+        pub max_attempts: u32,
+        /// The number of seconds added to the relative timeout of each retry.
+        /// The `n`-th retry times out `timeout_seconds + n * timeout_backoff_seconds` after it is sent.
+        #[serde(default)]
+        pub timeout_backoff_seconds: u64,
     }
 
     impl ChannelOpenInitOptions {
@@ -125,5 +531,23 @@ pub mod options {
                 .clone()
                 .unwrap_or_else(|| crate::ibc::types::keys::HOST_PORT_ID.to_string())
         }
+
+        /// Returns the tx encoding that is proposed to the ICA host.
+        #[must_use]
+        pub fn proposed_tx_encoding(&self) -> TxEncoding {
+            self.tx_encodings
+                .first()
+                .cloned()
+                .unwrap_or(TxEncoding::Protobuf)
+        }
+
+        /// Returns whether the given tx encoding is accepted by the contract.
+        #[must_use]
+        pub fn accepts_tx_encoding(&self, encoding: &TxEncoding) -> bool {
+            if self.tx_encodings.is_empty() {
+                return encoding == &TxEncoding::Protobuf;
+            }
+            self.tx_encodings.contains(encoding)
+        }
     }
 }
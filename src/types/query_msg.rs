@@ -2,33 +2,46 @@
 
 use cosmwasm_std::{Empty, QueryRequest};
 
+use crate::types::ContractError;
+
 pub use response::*;
 
 /// Converts a [`QueryRequest`] to a grpc method path, protobuf bytes, and a flag indicating if the query is stargate.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the query type is not supported.
-#[must_use]
-pub fn query_to_protobuf(query: QueryRequest<Empty>) -> (String, Vec<u8>, bool) {
+/// Returns an error if the query type is not supported.
+pub fn query_to_protobuf(
+    query: QueryRequest<Empty>,
+) -> Result<(String, Vec<u8>, bool), ContractError> {
     match query {
         QueryRequest::Bank(bank_query) => convert_to_protobuf::bank(bank_query),
         #[allow(deprecated)]
-        QueryRequest::Stargate { path, data } => (path, data.into(), true),
+        QueryRequest::Stargate { path, data } => Ok((path, data.into(), true)),
         QueryRequest::Wasm(wasm_query) => convert_to_protobuf::wasm(wasm_query),
-        QueryRequest::Ibc(_) => panic!("ibc-go queries are not marked module safe (yet)"),
-        QueryRequest::Custom(_) => panic!("custom queries are not supported"),
+        QueryRequest::Ibc(_) => Err(ContractError::UnsupportedQuery(
+            "ibc-go queries are not marked module safe (yet)".to_string(),
+        )),
+        QueryRequest::Custom(_) => Err(ContractError::UnsupportedQuery(
+            "custom queries are not supported".to_string(),
+        )),
         #[cfg(feature = "staking")]
         QueryRequest::Staking(staking_query) => convert_to_protobuf::staking(staking_query),
         #[cfg(feature = "staking")]
-        QueryRequest::Distribution(_) => {
-            panic!("distribution queries are not marked module safe (yet)")
-        }
-        _ => panic!("Unsupported QueryRequest"),
+        QueryRequest::Distribution(_) => Err(ContractError::UnsupportedQuery(
+            "distribution queries are not marked module safe (yet)".to_string(),
+        )),
+        _ => Err(ContractError::UnsupportedQuery(
+            "unsupported QueryRequest".to_string(),
+        )),
     }
 }
 
 /// Converts [`proto::MsgModuleQuerySafeResponse`] to [`IcaQueryResult`] using the storage.
+///
+/// If all the responses are decoded successfully, then [`IcaQueryResult::Success`] is returned.
+/// Otherwise, [`IcaQueryResult::Partial`] is returned so that the successfully decoded
+/// responses can still be used.
 #[cfg(feature = "export")]
 #[must_use]
 pub fn result_from_response(
@@ -43,18 +56,45 @@ pub fn result_from_response(
         ));
     }
 
-    paths
+    let results: Vec<IcaQueryOutcome> = paths
         .into_iter()
         .zip(resp_msg.responses.iter())
-        .map(|((path, is_stargate), resp)| from_protobuf::response(&path, resp, is_stargate))
-        .collect::<Result<_, _>>()
-        .map_or_else(
-            |e| IcaQueryResult::Error(e.to_string()),
-            |responses| IcaQueryResult::Success {
-                height: resp_msg.height,
-                responses,
-            },
-        )
+        .map(|((path, is_stargate), resp)| {
+            match from_protobuf::response(&path, resp, is_stargate) {
+                Ok(response) => IcaQueryOutcome::Success {
+                    path,
+                    data: resp.clone().into(),
+                    response,
+                },
+                Err(err) => IcaQueryOutcome::Error {
+                    path,
+                    data: resp.clone().into(),
+                    error: err.to_string(),
+                },
+            }
+        })
+        .collect();
+
+    if results
+        .iter()
+        .all(|result| matches!(result, IcaQueryOutcome::Success { .. }))
+    {
+        IcaQueryResult::Success {
+            height: resp_msg.height,
+            responses: results
+                .into_iter()
+                .filter_map(|result| match result {
+                    IcaQueryOutcome::Success { response, .. } => Some(response),
+                    IcaQueryOutcome::Error { .. } => None,
+                })
+                .collect(),
+        }
+    } else {
+        IcaQueryResult::Partial {
+            height: resp_msg.height,
+            results,
+        }
+    }
 }
 
 /// The constants for the `query_msg` module.
@@ -88,12 +128,17 @@ pub mod constants {
 
     /// The query path for the `ContractInfo` query.
     pub const WASM_CONTRACT_INFO: &str = "/cosmwasm.wasm.v1.Query/ContractInfo";
-    /// The query path for the `CodeInfo` query.
+    /// The query path for the `Code` query. This query also returns the wasm bytecode.
     pub const WASM_CODE: &str = "/cosmwasm.wasm.v1.Query/Code";
+    /// The query path for the `CodeInfo` query.
+    pub const WASM_CODE_INFO: &str = "/cosmwasm.wasm.v1.Query/CodeInfo";
     /// The query path for the Raw query.
     pub const WASM_RAW: &str = "/cosmwasm.wasm.v1.Query/RawContractState";
     /// The query path for the Smart query.
     pub const WASM_SMART: &str = "/cosmwasm.wasm.v1.Query/SmartContractState";
+
+    /// The query path for the interchain accounts host `Params` query.
+    pub const ICA_HOST_PARAMS: &str = "/ibc.applications.interchain_accounts.host.v1.Query/Params";
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -110,11 +155,42 @@ mod response {
             /// The responses to the queries.
             responses: Vec<IcaQueryResponse>,
         },
+        /// The queries were executed, but at least one of the responses could not be decoded.
+        /// Each query is reported with its own result, along with the raw response bytes.
+        Partial {
+            /// The height of the block at which the queries were executed on the counterparty chain.
+            height: u64,
+            /// The results of the queries in the same order as the queries were sent.
+            results: Vec<IcaQueryOutcome>,
+        },
         /// The query failed with an error message. The error string
         /// often does not contain useful information for the end user.
         Error(String),
     }
 
+    /// The result of a single query in an [`IcaQueryResult::Partial`].
+    #[cw_serde]
+    pub enum IcaQueryOutcome {
+        /// The response was decoded successfully.
+        Success {
+            /// The query grpc method
+            path: String,
+            /// The raw protobuf encoded response bytes.
+            data: cosmwasm_std::Binary,
+            /// The decoded response.
+            response: IcaQueryResponse,
+        },
+        /// The response could not be decoded.
+        Error {
+            /// The query grpc method
+            path: String,
+            /// The raw protobuf encoded response bytes.
+            data: cosmwasm_std::Binary,
+            /// The decoding error message.
+            error: String,
+        },
+    }
+
     /// The response for a successful ICA query.
     #[non_exhaustive]
     #[cw_serde]
@@ -161,6 +237,7 @@ mod response {
         ContractInfo(Option<cosmwasm_std::ContractInfoResponse>),
         /// Response for the [`cosmwasm_std::WasmQuery::CodeInfo`] query.
         /// Returns `None` if the code does not exist.
+        /// Requires the host chain to be using wasmd `v0.54+`.
         CodeInfo(Option<cosmwasm_std::CodeInfoResponse>),
         /// Response for the [`cosmwasm_std::WasmQuery::Raw`] query.
         RawContractState(Option<cosmwasm_std::Binary>),
@@ -229,8 +306,10 @@ mod convert_to_protobuf {
 
     use super::constants;
 
-    pub fn bank(bank_query: BankQuery) -> (String, Vec<u8>, bool) {
-        match bank_query {
+    use crate::types::ContractError;
+
+    pub fn bank(bank_query: BankQuery) -> Result<(String, Vec<u8>, bool), ContractError> {
+        Ok(match bank_query {
             BankQuery::Balance { address, denom } => (
                 constants::BALANCE.to_string(),
                 QueryBalanceRequest { address, denom }.encode_to_vec(),
@@ -270,12 +349,16 @@ mod convert_to_protobuf {
                 QuerySupplyOfRequest { denom }.encode_to_vec(),
                 false,
             ),
-            _ => panic!("Unsupported BankQuery"),
-        }
+            _ => {
+                return Err(ContractError::UnsupportedQuery(
+                    "unsupported BankQuery".to_string(),
+                ))
+            }
+        })
     }
 
-    pub fn wasm(wasm_query: WasmQuery) -> (String, Vec<u8>, bool) {
-        match wasm_query {
+    pub fn wasm(wasm_query: WasmQuery) -> Result<(String, Vec<u8>, bool), ContractError> {
+        Ok(match wasm_query {
             WasmQuery::Raw { contract_addr, key } => (
                 constants::WASM_RAW.to_string(),
                 QueryRawContractStateRequest {
@@ -302,21 +385,29 @@ mod convert_to_protobuf {
                 .encode_to_vec(),
                 false,
             ),
-            WasmQuery::CodeInfo { .. } => {
-                panic!("CodeInfo query is not supported due to response size.")
+            WasmQuery::CodeInfo { code_id } => (
+                constants::WASM_CODE_INFO.to_string(),
+                super::proto::QueryCodeInfoRequest { code_id }.encode_to_vec(),
+                false,
+            ),
+            _ => {
+                return Err(ContractError::UnsupportedQuery(
+                    "unsupported WasmQuery".to_string(),
+                ))
             }
-            _ => panic!("Unsupported WasmQuery"),
-        }
+        })
     }
 
     #[cfg(feature = "staking")]
-    pub fn staking(staking_query: cosmwasm_std::StakingQuery) -> (String, Vec<u8>, bool) {
+    pub fn staking(
+        staking_query: cosmwasm_std::StakingQuery,
+    ) -> Result<(String, Vec<u8>, bool), ContractError> {
         use cosmos_sdk_proto::cosmos::staking::v1beta1::{
             QueryDelegationRequest, QueryDelegatorDelegationsRequest, QueryParamsRequest,
             QueryValidatorRequest, QueryValidatorsRequest,
         };
 
-        match staking_query {
+        Ok(match staking_query {
             cosmwasm_std::StakingQuery::Validator { address } => (
                 constants::VALIDATOR.to_string(),
                 QueryValidatorRequest {
@@ -360,8 +451,12 @@ mod convert_to_protobuf {
                 QueryParamsRequest::default().encode_to_vec(),
                 false,
             ),
-            _ => panic!("Unsupported StakingQuery"),
-        }
+            _ => {
+                return Err(ContractError::UnsupportedQuery(
+                    "unsupported StakingQuery".to_string(),
+                ))
+            }
+        })
     }
 }
 
@@ -388,9 +483,9 @@ pub mod from_protobuf {
         prost::Message,
     };
     use cosmwasm_std::{
-        AllBalanceResponse, AllDenomMetadataResponse, BalanceResponse, Binary, Coin,
-        ContractInfoResponse, DenomMetadata, DenomMetadataResponse, DenomUnit, StdResult,
-        SupplyResponse, Uint128,
+        AllBalanceResponse, AllDenomMetadataResponse, BalanceResponse, Binary, Checksum,
+        CodeInfoResponse, Coin, ContractInfoResponse, DenomMetadata, DenomMetadataResponse,
+        DenomUnit, StdError, StdResult, SupplyResponse, Uint128,
     };
 
     fn convert_to_coin(coin: ProtoCoin) -> StdResult<Coin> {
@@ -547,6 +642,21 @@ pub mod from_protobuf {
                     }),
                 )))
             }
+            constants::WASM_CODE_INFO => {
+                let resp = super::proto::QueryCodeInfoResponse::decode(resp)?;
+                Ok(IcaQueryResponse::Wasm(WasmQueryResponse::CodeInfo(
+                    if resp.checksum.is_empty() {
+                        None
+                    } else {
+                        Some(CodeInfoResponse::new(
+                            resp.code_id,
+                            cosmwasm_std::Addr::unchecked(resp.creator),
+                            Checksum::try_from(resp.checksum.as_slice())
+                                .map_err(|e| StdError::generic_err(e.to_string()))?,
+                        ))
+                    },
+                )))
+            }
             constants::WASM_RAW => {
                 let resp = QueryRawContractStateResponse::decode(resp)?;
                 Ok(IcaQueryResponse::Wasm(WasmQueryResponse::RawContractState(
@@ -695,8 +805,136 @@ pub mod proto {
         pub responses: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
     }
 
+    /// `QueryCodeInfoRequest` is the request type for the `Query/CodeInfo` RPC method added in wasmd v0.54.
+    /// Unlike `Query/Code`, the response does not include the wasm bytecode.
+    #[derive(::prost::Message)]
+    pub struct QueryCodeInfoRequest {
+        /// `code_id` is the id of the stored code
+        #[prost(uint64, tag = "1")]
+        pub code_id: u64,
+    }
+
+    /// `QueryCodeInfoResponse` is the response type for the `Query/CodeInfo` RPC method.
+    #[derive(::prost::Message)]
+    pub struct QueryCodeInfoResponse {
+        /// `code_id` is the id of the stored code
+        #[prost(uint64, tag = "1")]
+        pub code_id: u64,
+        /// `creator` is the address that stored the code
+        #[prost(string, tag = "2")]
+        pub creator: ::prost::alloc::string::String,
+        /// `checksum` is the sha256 hash of the wasm bytecode
+        #[prost(bytes = "vec", tag = "3")]
+        pub checksum: ::prost::alloc::vec::Vec<u8>,
+    }
+
     impl ::prost::Name for MsgModuleQuerySafe {
         const NAME: &'static str = "MsgModuleQuerySafe";
         const PACKAGE: &'static str = "ibc.applications.interchain_accounts.host.v1";
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmos_sdk_proto::prost::Message;
+    use cosmwasm_std::{Addr, Binary, Checksum, CodeInfoResponse, WasmQuery};
+
+    use super::*;
+
+    #[test]
+    fn test_code_info_query() {
+        let (path, data, is_stargate) =
+            query_to_protobuf(QueryRequest::Wasm(WasmQuery::CodeInfo { code_id: 7 })).unwrap();
+        assert_eq!(path, constants::WASM_CODE_INFO);
+        assert!(!is_stargate);
+        assert_eq!(
+            proto::QueryCodeInfoRequest::decode(data.as_slice())
+                .unwrap()
+                .code_id,
+            7
+        );
+
+        let checksum = Checksum::from([1; 32]);
+        let resp = proto::QueryCodeInfoResponse {
+            code_id: 7,
+            creator: "creator".to_string(),
+            checksum: Vec::from(checksum),
+        }
+        .encode_to_vec();
+
+        let result = from_protobuf::response(&path, &resp, is_stargate).unwrap();
+        assert_eq!(
+            result,
+            IcaQueryResponse::Wasm(WasmQueryResponse::CodeInfo(Some(CodeInfoResponse::new(
+                7,
+                Addr::unchecked("creator"),
+                checksum,
+            ))))
+        );
+
+        // An empty response means the code was not found.
+        let result = from_protobuf::response(&path, Binary::default().as_slice(), false).unwrap();
+        assert_eq!(
+            result,
+            IcaQueryResponse::Wasm(WasmQueryResponse::CodeInfo(None))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "export")]
+    fn test_result_from_response() {
+        use cosmos_sdk_proto::cosmos::bank::v1beta1::QueryBalanceResponse;
+
+        let balance_resp = QueryBalanceResponse {
+            balance: Some(cosmos_sdk_proto::cosmos::base::v1beta1::Coin {
+                denom: "uatom".to_string(),
+                amount: "100".to_string(),
+            }),
+        }
+        .encode_to_vec();
+        // The amount cannot be parsed, so this response fails to decode.
+        let invalid_resp = QueryBalanceResponse {
+            balance: Some(cosmos_sdk_proto::cosmos::base::v1beta1::Coin {
+                denom: "uatom".to_string(),
+                amount: "invalid".to_string(),
+            }),
+        }
+        .encode_to_vec();
+
+        let paths = vec![(constants::BALANCE.to_string(), false)];
+        let result = result_from_response(
+            paths.clone(),
+            &proto::MsgModuleQuerySafeResponse {
+                height: 10,
+                responses: vec![balance_resp.clone()],
+            },
+        );
+        assert!(
+            matches!(result, IcaQueryResult::Success { height: 10, ref responses } if responses.len() == 1)
+        );
+
+        let paths = vec![paths[0].clone(), paths[0].clone()];
+        let result = result_from_response(
+            paths,
+            &proto::MsgModuleQuerySafeResponse {
+                height: 10,
+                responses: vec![balance_resp.clone(), invalid_resp.clone()],
+            },
+        );
+        let IcaQueryResult::Partial { height, results } = result else {
+            panic!("expected a partial result, got {result:?}");
+        };
+        assert_eq!(height, 10);
+        assert_eq!(results.len(), 2);
+        assert!(matches!(
+            &results[0],
+            IcaQueryOutcome::Success { path, data, .. }
+                if path == constants::BALANCE && data.as_slice() == balance_resp.as_slice()
+        ));
+        assert!(matches!(
+            &results[1],
+            IcaQueryOutcome::Error { path, data, .. }
+                if path == constants::BALANCE && data.as_slice() == invalid_resp.as_slice()
+        ));
+    }
+}
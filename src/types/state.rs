@@ -1,25 +1,67 @@
 //! This module defines the state storage of the Contract.
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, IbcChannel};
-use cw_storage_plus::Item;
+use cosmwasm_std::{Addr, IbcChannel, IbcPacket, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
 
-use super::{msg::options::ChannelOpenInitOptions, ContractError};
+use super::{
+    msg::options::{AutoReopenPolicy, ChannelOpenInitOptions},
+    ContractError,
+};
 
 #[allow(clippy::module_name_repetitions)]
-pub use channel::{ChannelState, ChannelStatus};
+pub use channel::{ChannelCloseReason, ChannelRecord, ChannelState, ChannelStatus};
 #[allow(clippy::module_name_repetitions)]
-pub use contract::State as ContractState;
+pub use contract::{IcaInfo, State as ContractState};
 
 /// The item used to store the state of the IBC application.
 pub const STATE: Item<ContractState> = Item::new("state");
 
-/// The item used to store the state of the IBC application's channel.
-pub const CHANNEL_STATE: Item<ChannelState> = Item::new("ica_channel");
+/// The map used to store the Interchain Account (ICA) info needed to send packets.
+/// It maps the `ica_id` to the ICA info, which is set during the handshake.
+pub const ICA_INFO: Map<u64, IcaInfo> = Map::new("ica_info");
 
-/// The item used to store the channel open init options.
-pub const CHANNEL_OPEN_INIT_OPTIONS: Item<ChannelOpenInitOptions> =
-    Item::new("channel_open_init_options");
+/// The map used to store the state of each ICA's channel.
+/// It maps the `ica_id` to the channel state.
+pub const CHANNEL_STATE: Map<u64, ChannelState> = Map::new("ica_channels");
+
+/// The map used to store the channel open init options of each ICA.
+/// It maps the `ica_id` to the options.
+pub const CHANNEL_OPEN_INIT_OPTIONS: Map<u64, ChannelOpenInitOptions> =
+    Map::new("ica_channel_open_init_options");
+
+/// The map used to store the history of the channels of each ICA, in the order they were opened.
+/// It maps the `ica_id` and the index of the channel to its record. Records are never deleted.
+pub const CHANNEL_HISTORY: Map<(u64, u64), ChannelRecord> = Map::new("ica_channel_history");
+
+/// The map used to store the ICAs whose next channel handshake may change their address.
+/// It is set by the owner in `CreateChannel`, and removed once the handshake completes.
+pub const ALLOW_NEW_ICA_ADDRESS: Map<u64, bool> = Map::new("allow_new_ica_address");
+
+/// The map used to store the auto reopen policy of each ICA.
+/// It maps the `ica_id` to the policy, and is used with the [`CHANNEL_OPEN_INIT_OPTIONS`].
+pub const AUTO_REOPEN_POLICY: Map<u64, AutoReopenPolicy> = Map::new("ica_auto_reopen_policies");
+
+/// The map used to count the automatic reopen attempts of each ICA since its last successful
+/// channel open handshake.
+pub const AUTO_REOPEN_ATTEMPTS: Map<u64, u32> = Map::new("ica_auto_reopen_attempts");
+
+/// The map used to find the ICA that a connection is reserved for.
+///
+/// The ICA host derives the ICA address from the connection and the controller port. Since this
+/// contract is bound to a single port, each ICA must use a different connection, which also keeps
+/// the ICA address stable when its channel is reopened.
+pub const CONNECTION_ICA_ID: Map<&str, u64> = Map::new("connection_ica_ids");
+
+/// The map used to find the ICA that a channel belongs to.
+/// It maps the `channel_id` to the `ica_id` and is saved during `OpenInit`.
+pub const CHANNEL_ICA_ID: Map<&str, u64> = Map::new("channel_ica_ids");
+
+/// The item used to store whether the contract was migrated from a version with a single ICA.
+///
+/// Only the last channel of such a contract is in [`CHANNEL_ICA_ID`], so the older channels
+/// are resolved to the ICA with `ica_id` `0`.
+pub const MIGRATED_SINGLE_ICA: Item<bool> = Item::new("migrated_single_ica");
 
 /// The item used to store whether or not channel open init is allowed.
 /// Used to prevent relayers from opening channels. This right is reserved to the contract.
@@ -29,87 +71,346 @@ pub const ALLOW_CHANNEL_OPEN_INIT: Item<bool> = Item::new("allow_channel_open_in
 /// Used to prevent relayers from closing channels. This right is reserved to the contract.
 pub const ALLOW_CHANNEL_CLOSE_INIT: Item<bool> = Item::new("allow_channel_close_init");
 
-/// The item used to store the paths of an ICA query until its `SendPacket` response is received.
+/// The item used to store the counterparty ports that are allowed as ICA hosts, other than
+/// [`HOST_PORT_ID`](crate::ibc::types::keys::HOST_PORT_ID) which is always allowed.
+///
+/// This allows custom ICA host implementations, such as wasm-based hosts on `wasm.*` ports.
+pub const ALLOWED_HOST_PORTS: Item<Vec<String>> = Item::new("allowed_host_ports");
+
+/// The item used to store the timeout in seconds of the packets that are sent without a timeout.
+///
+/// If it is not set, then [`DEFAULT_TIMEOUT_SECONDS`](crate::ibc::types::packet::DEFAULT_TIMEOUT_SECONDS) is used.
+pub const DEFAULT_TIMEOUT: Item<u64> = Item::new("default_timeout_seconds");
+
+/// The item used to store the addresses that are allowed to add messages to the queues of the ICAs
+/// with [`ExecuteMsg::Enqueue`](crate::types::msg::ExecuteMsg::Enqueue), other than the owner.
+pub const ENQUEUERS: Item<Vec<Addr>> = Item::new("enqueuers");
+
+/// The map used to store the messages queued for each ICA until they are flushed.
+///
+/// It maps the `ica_id` and the id of an entry to the entry, so that the entries of an ICA are
+/// ranged in the order they were added.
+///
+/// The queue is emptied by [`ExecuteMsg::Flush`](crate::types::msg::ExecuteMsg::Flush) and
+/// [`ExecuteMsg::ClearQueue`](crate::types::msg::ExecuteMsg::ClearQueue).
+pub const QUEUE: Map<(u64, u64), queue::QueueEntry> = Map::new("ica_queue_entries");
+
+/// `QUEUE_INFO` maps the `ica_id` to the next entry id, the length and the size of its queue, so
+/// that an entry is added without loading the other entries.
+pub const QUEUE_INFO: Map<u64, queue::QueueInfo> = Map::new("ica_queue_info");
+
+/// `PENDING_CORRELATIONS` maps the `channel_id` and sequence of an in-flight flushed packet to the
+/// correlation ids of its queue entries, which are returned in the packet callbacks.
+pub const PENDING_CORRELATIONS: Map<(&str, u64), Vec<queue::Correlation>> =
+    Map::new("pending_correlations");
+
+/// `PACKET_RETRIES` maps the `channel_id` and sequence of an in-flight packet to its retry policy,
+/// if it was sent with one.
+pub const PACKET_RETRIES: Map<(&str, u64), retry::PacketRetry> = Map::new("packet_retries");
+
+/// `SCHEDULED_RETRIES` maps the `ica_id` to the packets that timed out on an ordered channel,
+/// which are resent once the channel is reopened.
+pub const SCHEDULED_RETRIES: Map<u64, Vec<retry::ScheduledRetry>> = Map::new("scheduled_retries");
+
+/// `RESENT_PACKETS` maps the id of a resent packet in [`QUERY`] to the packet that timed out.
+///
+/// It is kept until the reply of the resend is received. If the resend fails, the timeout
+/// callback of that packet is sent instead.
+pub const RESENT_PACKETS: Map<u64, retry::TimedOutPacket> = Map::new("resent_packets");
+
+/// The map used to store the cached list of message type URLs that each ICA host allows.
+///
+/// It is fetched from the host's `icahost` params with
+/// [`ExecuteMsg::RefreshHostAllowMessages`](crate::types::msg::ExecuteMsg::RefreshHostAllowMessages).
+/// If it is not set, then the messages are not validated before they are sent.
+pub const HOST_ALLOW_MESSAGES: Map<u64, Vec<String>> = Map::new("host_allow_messages_by_ica");
+
+/// The map used to store the ICA packets until their `SendPacket` responses are received.
 ///
-/// Once the response is received, it is moved to the [`PENDING_QUERIES`] map and deleted from this item.
+/// It maps a unique query id to the packet. The query id is sent as the payload of the
+/// [`cosmwasm_std::SubMsg`] so that any number of query packets can be sent in a single transaction.
+///
+/// Once the response is received, the paths are moved to the [`PENDING_QUERIES`] map and deleted from this map.
 /// This is used to ensure that the correct sequence is recorded for the response.
 #[cfg(feature = "query")]
-pub const QUERY: Item<Vec<(String, bool)>> = Item::new("pending_query");
+pub const QUERY: Map<u64, ica_query::QueryPacket> = Map::new("pending_query_packets");
+
+/// The item used to store the next query id to be used as a key in [`QUERY`].
+#[cfg(feature = "query")]
+pub const NEXT_QUERY_ID: Item<u64> = Item::new("next_query_id");
+
+/// `PENDING_PACKETS` is the registry of the packets sent by the contract that have not been
+/// acknowledged or timed out yet. It maps the `channel_id` and sequence to the packet.
+pub const PENDING_PACKETS: Map<(&str, u64), packet::PendingPacket> = Map::new("pending_packets");
+
+/// `PACKET_HISTORY` is the log of the packets sent by the contract that were acknowledged or
+/// timed out. It maps an increasing index to the outcome of the packet.
+///
+/// The oldest outcomes are removed once the log is longer than the retention limit.
+pub const PACKET_HISTORY: Map<u64, packet::PacketOutcome> = Map::new("packet_history");
+
+/// `PACKET_HISTORY_BY_STATUS` indexes the [`PACKET_HISTORY`] by the status of the outcomes and
+/// their index, so that the history is filtered by status without scanning the other outcomes.
+pub const PACKET_HISTORY_BY_STATUS: Map<(&str, u64), cosmwasm_std::Empty> =
+    Map::new("packet_history_by_status");
+
+/// The item used to store the index of the oldest outcome in [`PACKET_HISTORY`] and the index of
+/// the next one.
+pub const PACKET_HISTORY_BOUNDS: Item<(u64, u64)> = Item::new("packet_history_bounds");
+
+/// The item used to store the maximum number of outcomes kept in [`PACKET_HISTORY`].
+///
+/// If it is not set, then [`DEFAULT_PACKET_HISTORY_RETENTION`](crate::types::msg::DEFAULT_PACKET_HISTORY_RETENTION) is used.
+pub const PACKET_HISTORY_RETENTION: Item<u64> = Item::new("packet_history_retention");
+
+/// The maximum number of outcomes removed from [`PACKET_HISTORY`] each time an outcome is recorded.
+/// This bounds the gas used by the acknowledgements after the retention limit is lowered.
+const PACKET_HISTORY_PRUNE_LIMIT: u64 = 10;
 
 /// `PENDING_QUERIES` is the map of pending queries.
 /// It maps `channel_id`, and sequence to the query path.
 #[cfg(feature = "query")]
-pub const PENDING_QUERIES: cw_storage_plus::Map<(&str, u64), Vec<(String, bool)>> =
-    cw_storage_plus::Map::new("pending_queries");
+pub const PENDING_QUERIES: Map<(&str, u64), Vec<(String, bool)>> = Map::new("pending_queries");
+
+/// The map used to store the recurring query subscriptions registered by the owner.
+/// It maps the subscription id to the subscription.
+#[cfg(feature = "query")]
+pub const SUBSCRIPTIONS: Map<u64, ica_query::Subscription> = Map::new("query_subscriptions");
+
+/// `SUBSCRIPTION_SCHEDULE` indexes the query subscriptions by their next run in nanoseconds and
+/// their id, so that the crank only reads the due subscriptions.
+#[cfg(feature = "query")]
+pub const SUBSCRIPTION_SCHEDULE: Map<(u64, u64), cosmwasm_std::Empty> =
+    Map::new("query_subscription_schedule");
+
+/// The item used to store the next subscription id to be used as a key in [`SUBSCRIPTIONS`].
+#[cfg(feature = "query")]
+pub const NEXT_SUBSCRIPTION_ID: Item<u64> = Item::new("next_subscription_id");
+
+/// `PENDING_SUBSCRIPTION_PACKETS` maps the `channel_id` and sequence of an in-flight
+/// subscription packet to its subscription id.
+#[cfg(feature = "query")]
+pub const PENDING_SUBSCRIPTION_PACKETS: Map<(&str, u64), u64> =
+    Map::new("pending_subscription_packets");
+
+/// The map used to store the latest result of each subscription that caches its results.
+#[cfg(feature = "query")]
+pub const SUBSCRIPTION_RESULTS: Map<u64, ica_query::SubscriptionResult> =
+    Map::new("query_subscription_results");
+
+/// Loads the ICA info of the given ICA.
+///
+/// # Errors
+///
+/// Returns an error if the ICA info is not set.
+pub fn load_ica_info(storage: &dyn Storage, ica_id: u64) -> Result<IcaInfo, ContractError> {
+    ICA_INFO
+        .may_load(storage, ica_id)?
+        .ok_or(ContractError::IcaInfoNotSet)
+}
+
+/// Records the outcome of a packet in the [`PACKET_HISTORY`], and removes the oldest outcomes
+/// that exceed the retention limit.
+///
+/// # Errors
+///
+/// Returns an error if the packet history cannot be loaded or saved.
+pub fn record_packet_outcome(
+    storage: &mut dyn Storage,
+    outcome: &packet::PacketOutcome,
+) -> StdResult<()> {
+    let retention = PACKET_HISTORY_RETENTION
+        .may_load(storage)?
+        .unwrap_or(crate::types::msg::DEFAULT_PACKET_HISTORY_RETENTION);
+    let (mut first, mut next) = PACKET_HISTORY_BOUNDS.may_load(storage)?.unwrap_or_default();
+
+    if retention > 0 {
+        PACKET_HISTORY.save(storage, next, outcome)?;
+        PACKET_HISTORY_BY_STATUS.save(
+            storage,
+            (outcome.status.as_str(), next),
+            &cosmwasm_std::Empty {},
+        )?;
+        next += 1;
+    }
+
+    let mut pruned = 0;
+    while next - first > retention && pruned < PACKET_HISTORY_PRUNE_LIMIT {
+        if let Some(pruned_outcome) = PACKET_HISTORY.may_load(storage, first)? {
+            PACKET_HISTORY_BY_STATUS.remove(storage, (pruned_outcome.status.as_str(), first));
+        }
+        PACKET_HISTORY.remove(storage, first);
+        first += 1;
+        pruned += 1;
+    }
+
+    PACKET_HISTORY_BOUNDS.save(storage, &(first, next))
+}
+
+/// Removes and returns the correlation ids of an in-flight packet.
+/// Returns an empty list if the packet was not sent by `Flush`.
+///
+/// # Errors
+///
+/// Returns an error if the correlation ids cannot be loaded.
+pub fn take_pending_correlations(
+    storage: &mut dyn Storage,
+    packet: &IbcPacket,
+) -> StdResult<Vec<queue::Correlation>> {
+    let key = (packet.src.channel_id.as_str(), packet.sequence);
+    let correlations = PENDING_CORRELATIONS
+        .may_load(storage, key)?
+        .unwrap_or_default();
+    PENDING_CORRELATIONS.remove(storage, key);
+
+    Ok(correlations)
+}
+
+/// Removes the retry policy of an in-flight packet, and returns the number of times the packet
+/// was resent. Returns `0` if the packet was not sent with a retry policy.
+///
+/// # Errors
+///
+/// Returns an error if the retry policy cannot be loaded.
+pub fn take_packet_retries(storage: &mut dyn Storage, packet: &IbcPacket) -> StdResult<u32> {
+    let key = (packet.src.channel_id.as_str(), packet.sequence);
+    let retries = PACKET_RETRIES
+        .may_load(storage, key)?
+        .map_or(0, |retry| retry.attempts);
+    PACKET_RETRIES.remove(storage, key);
+
+    Ok(retries)
+}
+
+/// Returns whether the given counterparty port is allowed as an ICA host port.
+///
+/// # Errors
+///
+/// Returns an error if the allowed host ports cannot be loaded.
+pub fn is_host_port_allowed(storage: &dyn Storage, port_id: &str) -> StdResult<bool> {
+    if port_id == crate::ibc::types::keys::HOST_PORT_ID {
+        return Ok(true);
+    }
+
+    Ok(ALLOWED_HOST_PORTS
+        .may_load(storage)?
+        .is_some_and(|ports| ports.iter().any(|port| port == port_id)))
+}
+
+/// Appends the record of a newly opened channel to the history of the ICA.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be loaded or saved.
+pub fn push_channel_record(
+    storage: &mut dyn Storage,
+    ica_id: u64,
+    record: &ChannelRecord,
+) -> StdResult<()> {
+    let index = CHANNEL_HISTORY
+        .prefix(ica_id)
+        .keys(storage, None, None, cosmwasm_std::Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(0, |last| last + 1);
+
+    CHANNEL_HISTORY.save(storage, (ica_id, index), record)
+}
+
+/// Records the closing of a channel in the history of the ICA.
+/// Does nothing if the channel is not the last channel recorded for the ICA.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be loaded or saved.
+pub fn close_channel_record(
+    storage: &mut dyn Storage,
+    ica_id: u64,
+    channel_id: &str,
+    close_height: u64,
+    reason: ChannelCloseReason,
+) -> StdResult<()> {
+    let last = CHANNEL_HISTORY
+        .prefix(ica_id)
+        .range(storage, None, None, cosmwasm_std::Order::Descending)
+        .next()
+        .transpose()?;
+
+    if let Some((index, mut record)) = last {
+        if record.channel_id == channel_id && record.close_height.is_none() {
+            record.close_height = Some(close_height);
+            record.close_reason = Some(reason);
+            CHANNEL_HISTORY.save(storage, (ica_id, index), &record)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the id of the ICA that the given channel belongs to.
+/// The unknown channels of a contract migrated from a single ICA belong to `ica_id` `0`.
+///
+/// # Errors
+///
+/// Returns an error if the channel does not belong to any ICA of this contract.
+pub fn load_channel_ica_id(storage: &dyn Storage, channel_id: &str) -> Result<u64, ContractError> {
+    if let Some(ica_id) = CHANNEL_ICA_ID.may_load(storage, channel_id)? {
+        return Ok(ica_id);
+    }
+    if MIGRATED_SINGLE_ICA.may_load(storage)?.unwrap_or_default() {
+        return Ok(0);
+    }
+
+    Err(ContractError::InvalidChannelInContractState)
+}
 
 mod contract {
-    use crate::ibc::types::metadata::TxEncoding;
+    use crate::{ibc::types::metadata::TxEncoding, types::msg::options::IcaBackend};
 
     use cosmwasm_schema::schemars::JsonSchema;
 
-    use super::{cw_serde, Addr, ContractError};
+    use super::{cw_serde, Addr};
 
     /// State is the state of the contract.
     #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, JsonSchema)]
     #[allow(clippy::derive_partial_eq_without_eq)]
     pub struct State {
-        /// The Interchain Account (ICA) info needed to send packets.
-        /// This is set during the handshake.
-        #[serde(default)]
-        pub ica_info: Option<IcaInfo>,
         /// The address of the callback contract.
         #[serde(default)]
         pub callback_address: Option<Addr>,
+        /// The backend that drives the interchain accounts.
+        #[serde(default)]
+        pub backend: IcaBackend,
     }
 
     impl State {
-        /// Creates a new [`State`]
+        /// Creates a new [`State`] that uses the [`IcaBackend::WasmPort`] backend.
         #[must_use]
         pub const fn new(callback_address: Option<Addr>) -> Self {
             Self {
-                ica_info: None,
-                // We always allow the first `MsgChannelOpenInit` message.
                 callback_address,
+                backend: IcaBackend::WasmPort,
             }
         }
-
-        /// Gets the ICA info
-        ///
-        /// # Errors
-        ///
-        /// Returns an error if the ICA info is not set.
-        pub fn get_ica_info(&self) -> Result<IcaInfo, ContractError> {
-            self.ica_info
-                .as_ref()
-                .map_or(Err(ContractError::IcaInfoNotSet), |s| Ok(s.clone()))
-        }
-
-        /// Sets the ICA info
-        pub fn set_ica_info(
-            &mut self,
-            ica_address: impl Into<String>,
-            channel_id: impl Into<String>,
-            encoding: TxEncoding,
-        ) {
-            self.ica_info = Some(IcaInfo::new(ica_address, channel_id, encoding));
-        }
-
-        /// Deletes the ICA info
-        pub fn delete_ica_info(&mut self) {
-            self.ica_info = None;
-        }
     }
 
-    /// IcaInfo is the ICA address and channel ID.
+    /// `IcaInfo` is the ICA address and channel ID.
     #[cw_serde]
     pub struct IcaInfo {
+        /// The address of the interchain account on the host chain.
         pub ica_address: String,
+        /// The channel ID of the ICA channel on this chain.
         pub channel_id: String,
+        /// The encoding of the transactions sent to the ICA host.
         pub encoding: TxEncoding,
+        /// The chain id of the host chain, if it was verified when the channel was created.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub host_chain_id: Option<String>,
     }
 
     impl IcaInfo {
         /// Creates a new [`IcaInfo`]
+        #[must_use]
         pub fn new(
             ica_address: impl Into<String>,
             channel_id: impl Into<String>,
@@ -119,6 +420,7 @@ mod contract {
                 ica_address: ica_address.into(),
                 channel_id: channel_id.into(),
                 encoding,
+                host_chain_id: None,
             }
         }
     }
@@ -139,7 +441,7 @@ mod channel {
         /// Init is the state of the channel when it is created.
         #[serde(rename = "STATE_INIT")]
         Init,
-        /// TryOpen is the state of the channel when it is trying to open.
+        /// `TryOpen` is the state of the channel when it is trying to open.
         #[serde(rename = "STATE_TRYOPEN")]
         TryOpen,
         /// Open is the state of the channel when it is open.
@@ -185,7 +487,7 @@ mod channel {
         }
 
         /// Closes the channel
-        pub fn close(&mut self) {
+        pub const fn close(&mut self) {
             self.channel_status = ChannelStatus::Closed;
         }
 
@@ -196,6 +498,38 @@ mod channel {
         }
     }
 
+    /// `ChannelCloseReason` is the reason a channel was closed.
+    #[cw_serde]
+    pub enum ChannelCloseReason {
+        /// The owner closed the channel with `MsgChannelCloseInit`.
+        CloseInit,
+        /// The counterparty closed the channel, and this chain confirmed it.
+        CloseConfirm,
+        /// A packet timed out on the ordered channel.
+        Timeout,
+        /// The channel was found closed on the chain by `SyncChannel`, so the reason is unknown.
+        Synced,
+    }
+
+    /// `ChannelRecord` is an entry of the channel history of an ICA.
+    #[cw_serde]
+    pub struct ChannelRecord {
+        /// The channel ID on this chain.
+        pub channel_id: String,
+        /// The address of the interchain account when the channel was opened.
+        pub ica_address: String,
+        /// The order of the channel.
+        pub ordering: IbcOrder,
+        /// The version metadata of the channel.
+        pub version: String,
+        /// The block height at which the channel open handshake completed.
+        pub open_height: u64,
+        /// The block height at which the channel was closed, if it was closed.
+        pub close_height: Option<u64>,
+        /// The reason the channel was closed, if it was closed.
+        pub close_reason: Option<ChannelCloseReason>,
+    }
+
     impl std::fmt::Display for ChannelStatus {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             match self {
@@ -214,9 +548,13 @@ mod channel {
 /// This module defines the types stored in the state for ICA queries.
 #[cfg(feature = "query")]
 pub mod ica_query {
+    use cosmwasm_std::{Coin, Empty, QueryRequest, Timestamp};
+
+    use crate::types::query_msg::IcaQueryResult;
+
     use super::cw_serde;
 
-    /// PendingQuery is the query packet that is pending a response.
+    /// `PendingQuery` is the query packet that is pending a response.
     #[cw_serde]
     pub struct PendingQuery {
         /// The source channel ID of the query packet.
@@ -228,4 +566,286 @@ pub mod ica_query {
         /// Whether the query was [`cosmwasm_std::QueryRequest::Stargate`] or not.
         pub is_stargate: bool,
     }
+
+    /// `QueryPacket` is a packet that is waiting for its `SendPacket` response.
+    #[cw_serde]
+    pub struct QueryPacket {
+        /// The packet to record in the pending packets once its sequence is known.
+        pub packet: super::packet::PendingPacket,
+        /// The source channel ID of the query packet.
+        pub channel_id: String,
+        /// The gRPC paths of the queries, and whether they were [`cosmwasm_std::QueryRequest::Stargate`] or not.
+        pub paths: Vec<(String, bool)>,
+        /// The id of the subscription that sent the packet, if any.
+        pub subscription_id: Option<u64>,
+        /// The correlation ids of the queue entries that were flushed in the packet, if any.
+        #[serde(default)]
+        pub correlations: Vec<super::queue::Correlation>,
+        /// The retry policy of the packet, if any.
+        #[serde(default)]
+        pub retry: Option<super::retry::PacketRetry>,
+    }
+
+    /// `Subscription` is a set of queries that is sent to the ICA host every `interval_seconds`.
+    ///
+    /// Due subscriptions are sent by anyone with [`ExecuteMsg::Crank`](crate::types::msg::ExecuteMsg::Crank).
+    #[cw_serde]
+    pub struct Subscription {
+        /// The id of the ICA that sends the queries.
+        #[serde(default)]
+        pub ica_id: u64,
+        /// The queries to send to the ICA host.
+        pub queries: Vec<QueryRequest<Empty>>,
+        /// The minimum number of seconds between two query packets of this subscription.
+        pub interval_seconds: u64,
+        /// The earliest block time at which the subscription can be cranked.
+        pub next_run: Timestamp,
+        /// Whether the latest result is saved in [`super::SUBSCRIPTION_RESULTS`].
+        pub cache_results: bool,
+        /// The bounty paid by this contract to the sender of each crank of this subscription.
+        pub bounty: Option<Coin>,
+    }
+
+    /// `SubscriptionResult` is the latest result of a subscription.
+    #[cw_serde]
+    pub struct SubscriptionResult {
+        /// The sequence number of the query packet that produced this result.
+        pub sequence: u64,
+        /// The result of the queries.
+        pub result: IcaQueryResult,
+    }
+}
+
+/// This module defines the types of the registry of the packets sent by the contract.
+pub mod packet {
+    use cosmwasm_std::{Addr, HexBinary, IbcTimeout};
+
+    use super::cw_serde;
+
+    /// `PacketStatus` is the final status of a packet sent by the contract.
+    #[cw_serde]
+    pub enum PacketStatus {
+        /// The packet was executed successfully on the host chain.
+        Success,
+        /// The packet failed to execute on the host chain.
+        Error,
+        /// The packet timed out.
+        Timeout,
+    }
+
+    impl PacketStatus {
+        /// Returns the status as it is serialized.
+        #[must_use]
+        pub const fn as_str(&self) -> &'static str {
+            match self {
+                Self::Success => "success",
+                Self::Error => "error",
+                Self::Timeout => "timeout",
+            }
+        }
+    }
+
+    /// `PacketOutcome` is an entry of the packet history.
+    #[cw_serde]
+    pub struct PacketOutcome {
+        /// The id of the ICA that sent the packet.
+        pub ica_id: u64,
+        /// The source channel ID of the packet.
+        pub channel_id: String,
+        /// The sequence number of the packet.
+        pub sequence: u64,
+        /// The final status of the packet.
+        pub status: PacketStatus,
+        /// The error returned by the host chain if the status is [`PacketStatus::Error`].
+        pub error: Option<String>,
+        /// The SHA-256 hash of the acknowledgement, which is the acknowledgement commitment of
+        /// the host chain. Not set if the packet timed out.
+        pub ack_hash: Option<HexBinary>,
+        /// The relayer that submitted the acknowledgement or the timeout.
+        pub relayer: Addr,
+        /// The block height of this chain at which the acknowledgement or timeout was received.
+        pub completion_height: u64,
+        /// The packet as it was recorded when it was sent, which includes its sender and send
+        /// height. Not set for the packets sent before the pending packets were recorded.
+        pub packet: Option<PendingPacket>,
+    }
+
+    /// `PendingPacket` is a packet sent by the contract that has not been acknowledged or timed
+    /// out yet.
+    #[cw_serde]
+    pub struct PendingPacket {
+        /// The id of the ICA that sent the packet.
+        pub ica_id: u64,
+        /// The source channel ID of the packet.
+        pub channel_id: String,
+        /// The sequence number of the packet.
+        pub sequence: u64,
+        /// The address whose message sent the packet.
+        pub sender: Addr,
+        /// The type URLs of the messages in the packet, in order.
+        pub type_urls: Vec<String>,
+        /// The memo of the packet.
+        pub memo: Option<String>,
+        /// The timeout of the packet.
+        pub timeout: IbcTimeout,
+        /// The block height of this chain at which the packet was sent.
+        pub send_height: u64,
+    }
+}
+
+/// This module defines the types used to queue messages and flush them in a single packet.
+pub mod queue {
+    use cosmwasm_std::{Addr, CosmosMsg, Empty, QueryRequest};
+
+    use super::cw_serde;
+
+    /// `QueueEntry` is a set of messages and queries added to the queue of an ICA by an enqueuer.
+    #[cw_serde]
+    pub struct QueueEntry {
+        /// The address that added the entry.
+        pub sender: Addr,
+        /// The correlation id given by the sender, if any.
+        pub correlation_id: Option<String>,
+        /// The messages of the entry.
+        pub messages: Vec<CosmosMsg>,
+        /// The queries of the entry.
+        pub queries: Vec<QueryRequest<Empty>>,
+        /// The size in bytes of the packet data of this entry alone, when it was added.
+        pub size: usize,
+    }
+
+    /// `QueueInfo` keeps the running totals of the queue of an ICA.
+    #[cw_serde]
+    #[derive(Default)]
+    pub struct QueueInfo {
+        /// The id of the next entry added to the queue.
+        pub next_entry_id: u64,
+        /// The number of entries in the queue.
+        pub length: u64,
+        /// The sum of the sizes of the entries in the queue. It is an upper bound of the size of
+        /// the packet data sent by [`ExecuteMsg::Flush`](crate::types::msg::ExecuteMsg::Flush).
+        pub size: usize,
+    }
+
+    /// `Correlation` locates the messages and queries of a queue entry in the flushed packet.
+    ///
+    /// The messages of a packet are executed in order, followed by the queries, so the responses
+    /// of an entry are found at the same indices of the acknowledgement.
+    #[cw_serde]
+    pub struct Correlation {
+        /// The address that added the entry.
+        pub sender: Addr,
+        /// The correlation id given by the sender, if any.
+        pub correlation_id: Option<String>,
+        /// The index of the first message of the entry in the packet.
+        pub first_message_index: u32,
+        /// The number of messages of the entry.
+        pub message_count: u32,
+        /// The index of the first query of the entry in the packet.
+        pub first_query_index: u32,
+        /// The number of queries of the entry.
+        pub query_count: u32,
+    }
+}
+
+/// This module defines the types used to resend the packets that timed out.
+pub mod retry {
+    use cosmwasm_std::{Addr, IbcPacket};
+
+    use crate::{ibc::types::packet::IcaPacketData, types::msg::options::RetryPolicy};
+
+    use super::cw_serde;
+
+    /// `PacketRetry` is the retry policy of an in-flight packet, and the packet data to resend.
+    #[cw_serde]
+    pub struct PacketRetry {
+        /// The retry policy given when the packet was sent.
+        pub policy: RetryPolicy,
+        /// The number of times the packet was resent.
+        pub attempts: u32,
+        /// The relative timeout in seconds of the first packet, to which the backoff is added.
+        /// The default timeout is used if the first packet had an absolute timeout.
+        pub timeout_seconds: u64,
+        /// The packet data as it was sent by the sender.
+        pub packet_data: IcaPacketData,
+    }
+
+    impl PacketRetry {
+        /// Returns whether the packet can be resent once more.
+        #[must_use]
+        pub const fn can_retry(&self) -> bool {
+            self.attempts < self.policy.max_attempts
+        }
+
+        /// Returns the relative timeout in seconds of the packet, with the backoff of its attempts.
+        #[must_use]
+        pub fn current_timeout_seconds(&self) -> u64 {
+            self.policy
+                .timeout_backoff_seconds
+                .saturating_mul(self.attempts.into())
+                .saturating_add(self.timeout_seconds)
+        }
+    }
+
+    /// `ScheduledRetry` is a packet that timed out and waits to be resent. It keeps the
+    /// in-flight state of the packet so that the retry is handled like the original packet.
+    #[cw_serde]
+    pub struct ScheduledRetry {
+        /// The retry policy and the packet data.
+        pub retry: PacketRetry,
+        /// The address that sent the packet.
+        pub sender: Addr,
+        /// The gRPC paths of the queries of the packet.
+        pub paths: Vec<(String, bool)>,
+        /// The id of the subscription that sent the packet, if any.
+        pub subscription_id: Option<u64>,
+        /// The correlation ids of the queue entries that were flushed in the packet, if any.
+        pub correlations: Vec<super::queue::Correlation>,
+        /// The packet that timed out, which is reported in the timeout callback if it is not
+        /// resent.
+        pub timed_out: TimedOutPacket,
+    }
+
+    /// `TimedOutPacket` is a packet that timed out, and the relayer of its timeout.
+    #[cw_serde]
+    pub struct TimedOutPacket {
+        /// The packet that timed out.
+        pub packet: IbcPacket,
+        /// The relayer that submitted the timeout.
+        pub relayer: Addr,
+    }
+}
+
+/// This module defines the state of the contract before it supported multiple ICAs.
+/// It is only used to migrate the state of the contract to the [`ICA_INFO`] and the other maps.
+pub mod legacy {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::Addr;
+    use cw_storage_plus::Item;
+
+    use super::{ChannelOpenInitOptions, ChannelState, IcaInfo};
+
+    /// `State` is the state of the contract before it supported multiple ICAs.
+    #[cw_serde]
+    pub struct State {
+        /// The ICA info of the only ICA of the contract.
+        #[serde(default)]
+        pub ica_info: Option<IcaInfo>,
+        /// The address of the callback contract.
+        #[serde(default)]
+        pub callback_address: Option<Addr>,
+    }
+
+    /// The item used to store the state of the contract.
+    pub const STATE: Item<State> = Item::new("state");
+
+    /// The item used to store the state of the only channel.
+    pub const CHANNEL_STATE: Item<ChannelState> = Item::new("ica_channel");
+
+    /// The item used to store the channel open init options.
+    pub const CHANNEL_OPEN_INIT_OPTIONS: Item<ChannelOpenInitOptions> =
+        Item::new("channel_open_init_options");
+
+    /// The item used to store the cached list of message type URLs that the ICA host allows.
+    pub const HOST_ALLOW_MESSAGES: Item<Vec<String>> = Item::new("host_allow_messages");
 }
@@ -62,7 +62,8 @@ mod execute {
         callback_msg: IcaControllerCallbackMsg,
     ) -> Result<Response, ContractError> {
         match &callback_msg {
-            IcaControllerCallbackMsg::OnChannelOpenAckCallback { .. } => Ok(Response::default()),
+            IcaControllerCallbackMsg::OnChannelOpenAckCallback { .. }
+            | IcaControllerCallbackMsg::OnChannelCloseCallback { .. } => Ok(Response::default()),
             IcaControllerCallbackMsg::OnAcknowledgementPacketCallback {
                 ica_acknowledgement,
                 ..
@@ -97,6 +97,8 @@ mod execute {
             owner: Some(env.contract.address.to_string()),
             channel_open_init_options,
             send_callbacks_to: Some(env.contract.address.to_string()),
+            backend: None,
+            allowed_host_ports: None,
         };
 
         let ica_count = ICA_COUNT.load(deps.storage).unwrap_or(0);
@@ -149,9 +151,12 @@ mod execute {
         });
 
         let ica_controller_msg = IcaControllerExecuteMsg::SendCosmosMsgs {
+            ica_id: 0,
             messages: vec![send_msg],
             packet_memo: None,
             timeout_seconds: None,
+            timeout: None,
+            retry: None,
         };
 
         let msg = cw_ica_contract.execute(ica_controller_msg)?;
@@ -168,23 +173,32 @@ mod execute {
         let ica_id = CONTRACT_ADDR_TO_ICA_ID.load(deps.storage, info.sender)?;
         let mut ica_state = ICA_STATES.load(deps.storage, ica_id)?;
 
-        if let IcaControllerCallbackMsg::OnChannelOpenAckCallback {
-            channel,
-            ica_address,
-            tx_encoding,
-        } = callback_msg
-        {
-            ica_state.ica_state = Some(state::IcaState {
-                ica_id,
-                channel_state: ChannelState {
-                    channel,
-                    channel_status: ChannelStatus::Open,
-                },
-                ica_addr: ica_address,
+        match callback_msg {
+            IcaControllerCallbackMsg::OnChannelOpenAckCallback {
+                channel,
+                ica_address,
                 tx_encoding,
-            });
-
-            ICA_STATES.save(deps.storage, ica_id, &ica_state)?;
+                ..
+            } => {
+                ica_state.ica_state = Some(state::IcaState {
+                    ica_id,
+                    channel_state: ChannelState {
+                        channel,
+                        channel_status: ChannelStatus::Open,
+                    },
+                    ica_addr: ica_address,
+                    tx_encoding,
+                });
+
+                ICA_STATES.save(deps.storage, ica_id, &ica_state)?;
+            }
+            IcaControllerCallbackMsg::OnChannelCloseCallback { .. } => {
+                if let Some(ica) = ica_state.ica_state.as_mut() {
+                    ica.channel_state.close();
+                    ICA_STATES.save(deps.storage, ica_id, &ica_state)?;
+                }
+            }
+            _ => {}
         }
 
         Ok(Response::default())